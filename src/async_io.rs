@@ -0,0 +1,190 @@
+/*!
+Supports of `tokio::io`.
+
+The `tokio` feature needs rustc 1.75 or later, because the traits return
+`impl Future` from their methods.
+
+# Examples
+
+```
+use vu64::async_io::{AsyncReadVu64, AsyncWriteVu64};
+# tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+let (mut client, mut server) = tokio::io::duplex(64);
+client.encode_and_write_vu64(0x0f0f_f0f0_0f0f_f0f0).await.unwrap();
+let r = server.read_and_decode_vu64().await;
+assert_eq!(r.unwrap(), 0x0f0f_f0f0_0f0f_f0f0);
+# });
+```
+
+The free functions are also available:
+
+```
+use vu64::async_io::{read_and_decode_vu64, write_and_encode_vu64};
+# tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+let (mut client, mut server) = tokio::io::duplex(64);
+write_and_encode_vu64(&mut client, 0x0f0f).await.unwrap();
+assert_eq!(read_and_decode_vu64(&mut server).await.unwrap(), 0x0f0f);
+# });
+```
+*/
+use super::signed::zigzag;
+use super::{decode_with_first_and_follow, decoded_len, encode, MAX_BYTES};
+use core::future::Future;
+use std::io::Result;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// async io read trait of `vu64` and `vi64`
+pub trait AsyncReadVu64: AsyncRead + Unpin {
+    /// reads `vu64` bytes and decods it to `u64`
+    ///
+    /// The first byte and the follow bytes may arrive in separate wakeups.
+    fn read_and_decode_vu64(&mut self) -> impl Future<Output = Result<u64>> + '_ {
+        async move {
+            let mut buf = [0u8; MAX_BYTES - 1];
+            let byte_1st = self.read_u8().await?;
+            let len = decoded_len(byte_1st);
+            if len > 1 {
+                self.read_exact(&mut buf[..len as usize - 1]).await?;
+            }
+            Ok(decode_with_first_and_follow(
+                len,
+                byte_1st,
+                &buf[..len as usize - 1],
+            )?)
+        }
+    }
+    /// reads `vi64` bytes and decods it to `i64`
+    fn read_and_decode_vi64(&mut self) -> impl Future<Output = Result<i64>> + '_ {
+        async move { self.read_and_decode_vu64().await.map(zigzag::decode) }
+    }
+}
+
+/// async io write trait of `vu64` and `vi64`
+pub trait AsyncWriteVu64: AsyncWrite + Unpin {
+    /// encods `u64` to `vu64` bytes and writes it.
+    ///
+    /// The at most 9 bytes are written with a single `write_all()`.
+    fn encode_and_write_vu64(&mut self, value: u64) -> impl Future<Output = Result<()>> + '_ {
+        async move {
+            let encoded = encode(value);
+            self.write_all(encoded.as_ref()).await
+        }
+    }
+    /// encods `i64` to `vi64` bytes and writes it.
+    fn encode_and_write_vi64(&mut self, value: i64) -> impl Future<Output = Result<()>> + '_ {
+        self.encode_and_write_vu64(zigzag::encode(value))
+    }
+}
+
+impl<T: AsyncRead + Unpin + ?Sized> AsyncReadVu64 for T {}
+impl<T: AsyncWrite + Unpin + ?Sized> AsyncWriteVu64 for T {}
+
+/// reads `vu64` bytes from `reader` and decods it to `u64`
+///
+/// This is the free function version of [`AsyncReadVu64::read_and_decode_vu64()`].
+#[inline]
+pub async fn read_and_decode_vu64<R: AsyncRead + Unpin + ?Sized>(reader: &mut R) -> Result<u64> {
+    reader.read_and_decode_vu64().await
+}
+
+/// reads `vi64` bytes from `reader` and decods it to `i64`
+#[inline]
+pub async fn read_and_decode_vi64<R: AsyncRead + Unpin + ?Sized>(reader: &mut R) -> Result<i64> {
+    reader.read_and_decode_vi64().await
+}
+
+/// encods `u64` to `vu64` bytes and writes it to `writer`.
+///
+/// This is the free function version of [`AsyncWriteVu64::encode_and_write_vu64()`].
+#[inline]
+pub async fn write_and_encode_vu64<W: AsyncWrite + Unpin + ?Sized>(
+    writer: &mut W,
+    value: u64,
+) -> Result<()> {
+    writer.encode_and_write_vu64(value).await
+}
+
+/// encods `i64` to `vi64` bytes and writes it to `writer`.
+#[inline]
+pub async fn write_and_encode_vi64<W: AsyncWrite + Unpin + ?Sized>(
+    writer: &mut W,
+    value: i64,
+) -> Result<()> {
+    writer.encode_and_write_vi64(value).await
+}
+
+#[cfg(test)]
+mod test_async_io {
+    use super::super::async_io::{AsyncReadVu64, AsyncWriteVu64};
+    #[tokio::test]
+    async fn vu64_duplex_1() {
+        let values = [0, 127, 128, 0x0f0f, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let (mut client, mut server) = tokio::io::duplex(16);
+        let writer = tokio::spawn(async move {
+            for &val in values.iter() {
+                client.encode_and_write_vu64(val).await.unwrap();
+            }
+        });
+        for &val in values.iter() {
+            assert_eq!(server.read_and_decode_vu64().await.unwrap(), val);
+        }
+        writer.await.unwrap();
+        let r = server.read_and_decode_vu64().await;
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+    #[tokio::test]
+    async fn vi64_duplex_1() {
+        let values = [0, -1, 1, i64::MIN, i64::MAX, -0x0f0f_f0f0];
+        let (mut client, mut server) = tokio::io::duplex(64);
+        for &val in values.iter() {
+            client.encode_and_write_vi64(val).await.unwrap();
+        }
+        for &val in values.iter() {
+            assert_eq!(server.read_and_decode_vi64().await.unwrap(), val);
+        }
+    }
+    #[tokio::test]
+    async fn vu64_read_err_truncated() {
+        let vec: Vec<u8> = vec![0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f];
+        let mut slice = vec.as_slice();
+        let r = slice.read_and_decode_vu64().await;
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+    #[tokio::test]
+    async fn vu64_read_err_redundant() {
+        let vec: Vec<u8> = vec![0xDD, 0, 0];
+        let mut slice = vec.as_slice();
+        let r = slice.read_and_decode_vu64().await;
+        assert_eq!(
+            format!("{}", r.unwrap_err()),
+            "redundant encoded vu64 value"
+        );
+    }
+    #[tokio::test]
+    async fn vu64_free_fn_split_arrival() {
+        use super::super::async_io::{read_and_decode_vu64, write_and_encode_vu64};
+        use tokio::io::AsyncWriteExt;
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let reader = tokio::spawn(async move {
+            let a = read_and_decode_vu64(&mut server).await.unwrap();
+            let b = read_and_decode_vu64(&mut server).await.unwrap();
+            (a, b)
+        });
+        // the first byte and the follow bytes arrive in separate wakeups
+        let enc = crate::encode(0x0f0f_f0f0_0f0f);
+        client.write_all(&enc.as_ref()[..1]).await.unwrap();
+        client.flush().await.unwrap();
+        tokio::task::yield_now().await;
+        client.write_all(&enc.as_ref()[1..]).await.unwrap();
+        write_and_encode_vu64(&mut client, 0x0f0f).await.unwrap();
+        assert_eq!(reader.await.unwrap(), (0x0f0f_f0f0_0f0f, 0x0f0f));
+    }
+    #[tokio::test]
+    async fn vi64_free_fn() {
+        use super::super::async_io::{read_and_decode_vi64, write_and_encode_vi64};
+        let mut vec: Vec<u8> = Vec::new();
+        write_and_encode_vi64(&mut vec, -0x0f0f).await.unwrap();
+        let mut slice = vec.as_slice();
+        assert_eq!(read_and_decode_vi64(&mut slice).await.unwrap(), -0x0f0f);
+    }
+}
@@ -0,0 +1,69 @@
+/*!
+Supports of `base64`.
+
+The text is the URL-safe base64 without padding of the encoded bytes,
+so it can be embedded in URLs and JSON text as it is.
+
+# Examples
+
+```
+use vu64::{encode, Vu64};
+let val = encode(0x0f0f);
+let text = val.to_base64();
+assert_eq!(text, "jzw");
+assert_eq!(Vu64::from_base64(&text), Ok(val));
+```
+*/
+use super::{decode_with_len, encode, Error, Vu64};
+use alloc::string::String;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+impl Vu64 {
+    /// Returns the URL-safe base64 text, without padding, of the encoded bytes.
+    #[inline]
+    pub fn to_base64(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.as_ref())
+    }
+
+    /// Parse the text written by [`to_base64()`](Vu64::to_base64).
+    ///
+    /// If the text is not a valid base64, `Error::InvalidBase64` is returned.
+    /// If the decoded bytes are followed by extra bytes, `Error::TrailingBytes` is returned.
+    pub fn from_base64(s: &str) -> Result<Vu64, Error> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(|_| Error::InvalidBase64)?;
+        let (val, len) = decode_with_len(&bytes)?;
+        if len != bytes.len() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(encode(val))
+    }
+}
+
+#[cfg(test)]
+mod test_base64 {
+    use super::super::{encode, Error, Vu64};
+    #[test]
+    fn round_trip_1() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX].iter() {
+            let vu64 = encode(val);
+            assert_eq!(Vu64::from_base64(&vu64.to_base64()), Ok(vu64));
+        }
+    }
+    #[test]
+    fn to_base64_examples() {
+        assert_eq!(encode(0).to_base64(), "AA");
+        assert_eq!(encode(u64::MAX).to_base64(), "____________");
+    }
+    #[test]
+    fn from_base64_err() {
+        assert_eq!(Vu64::from_base64("!!"), Err(Error::InvalidBase64));
+        assert_eq!(Vu64::from_base64(""), Err(Error::Truncated));
+        // 0xDD, 0x00, 0x00
+        assert_eq!(Vu64::from_base64("3QAA"), Err(Error::RedundantEncode));
+        // 0x01, 0x02
+        assert_eq!(Vu64::from_base64("AQI"), Err(Error::TrailingBytes));
+    }
+}
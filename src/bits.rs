@@ -0,0 +1,100 @@
+/*!
+Supports of `bitstream-io`.
+
+The `vu64` can be read from and written to an arbitrary bit offset of
+a bit-packed stream, as 8 bits per byte.
+
+# Examples
+
+```
+use bitstream_io::{BigEndian, BitRead, BitReader, BitWrite, BitWriter};
+use vu64::bits::{decode_from_bits, encode_to_bits};
+let mut writer = BitWriter::endian(Vec::new(), BigEndian);
+writer.write(3, 0b101u8).unwrap();
+encode_to_bits(&mut writer, 0x0f0f).unwrap();
+writer.byte_align().unwrap();
+let buf = writer.into_writer();
+//
+let mut reader = BitReader::endian(buf.as_slice(), BigEndian);
+assert_eq!(reader.read::<u8>(3).unwrap(), 0b101);
+assert_eq!(decode_from_bits(&mut reader), Ok(0x0f0f));
+```
+*/
+use super::{decode_with_first_and_follow, decoded_len, encode, Error, MAX_BYTES};
+use bitstream_io::{BitRead, BitWrite};
+
+/// reads `vu64` bits from `reader` and decods it to `u64`.
+///
+/// The first 8 bits are read as the first byte, and then the follow bytes
+/// as 8 bits each. If `reader` fails in the middle, `Error::Truncated` is returned.
+pub fn decode_from_bits<R: BitRead + ?Sized>(reader: &mut R) -> Result<u64, Error> {
+    let mut buf = [0u8; MAX_BYTES - 1];
+    let byte_1st: u8 = reader.read(8).map_err(|_| Error::Truncated)?;
+    let len = decoded_len(byte_1st);
+    let follow = &mut buf[..len as usize - 1];
+    reader.read_bytes(follow).map_err(|_| Error::Truncated)?;
+    decode_with_first_and_follow(len, byte_1st, follow)
+}
+
+/// encods `u64` to `vu64` bytes and writes it to `writer` as 8 bits per byte.
+pub fn encode_to_bits<W: BitWrite + ?Sized>(writer: &mut W, value: u64) -> std::io::Result<()> {
+    writer.write_bytes(encode(value).as_ref())
+}
+
+#[cfg(test)]
+mod test_bits {
+    use super::super::Error;
+    use super::{decode_from_bits, encode_to_bits};
+    use bitstream_io::{BigEndian, BitRead, BitReader, BitWrite, BitWriter, LittleEndian};
+    #[test]
+    fn unaligned_big_endian() {
+        let values = [0, 127, 0x0f0f, 0x0f0f_f0f0, u64::MAX];
+        for offset in 1..8 {
+            let mut writer = BitWriter::endian(Vec::new(), BigEndian);
+            writer.write(offset, 0u8).unwrap();
+            for &val in values.iter() {
+                encode_to_bits(&mut writer, val).unwrap();
+                writer.write_bit(true).unwrap();
+            }
+            writer.byte_align().unwrap();
+            let buf = writer.into_writer();
+            //
+            let mut reader = BitReader::endian(buf.as_slice(), BigEndian);
+            assert_eq!(reader.read::<u8>(offset).unwrap(), 0);
+            for &val in values.iter() {
+                assert_eq!(decode_from_bits(&mut reader), Ok(val));
+                assert!(reader.read_bit().unwrap());
+            }
+        }
+    }
+    #[test]
+    fn unaligned_little_endian() {
+        let mut writer = BitWriter::endian(Vec::new(), LittleEndian);
+        writer.write(5, 0b10101u8).unwrap();
+        encode_to_bits(&mut writer, 0x0f0f_f0f0_0f0f).unwrap();
+        writer.byte_align().unwrap();
+        let buf = writer.into_writer();
+        //
+        let mut reader = BitReader::endian(buf.as_slice(), LittleEndian);
+        assert_eq!(reader.read::<u8>(5).unwrap(), 0b10101);
+        assert_eq!(decode_from_bits(&mut reader), Ok(0x0f0f_f0f0_0f0f));
+    }
+    #[test]
+    fn decode_err_truncated() {
+        let mut writer = BitWriter::endian(Vec::new(), BigEndian);
+        writer.write(4, 0u8).unwrap();
+        writer.write_bytes(&[0xE0, 0x0f, 0xff]).unwrap();
+        writer.byte_align().unwrap();
+        let buf = writer.into_writer();
+        //
+        let mut reader = BitReader::endian(buf.as_slice(), BigEndian);
+        reader.skip(4).unwrap();
+        assert_eq!(decode_from_bits(&mut reader), Err(Error::Truncated));
+    }
+    #[test]
+    fn decode_err_redundant() {
+        let buf = [0xDD, 0, 0];
+        let mut reader = BitReader::endian(buf.as_slice(), BigEndian);
+        assert_eq!(decode_from_bits(&mut reader), Err(Error::RedundantEncode));
+    }
+}
@@ -0,0 +1,154 @@
+/*!
+Supports of `bytes`.
+
+# Examples
+
+```
+use vu64::buf::split_vu64;
+let mut buf = bytes::Bytes::from_static(&[0x01, 0x8F, 0x3c]);
+assert_eq!(split_vu64(&mut buf), Ok(1));
+assert_eq!(split_vu64(&mut buf), Ok(0x0f0f));
+assert!(buf.is_empty());
+```
+
+```
+use bytes::BytesMut;
+use vu64::buf::{get_vi64, get_vu64, put_vi64, put_vu64};
+let mut buf = BytesMut::new();
+put_vu64(&mut buf, 0x0f0f);
+put_vi64(&mut buf, -1);
+assert_eq!(buf.as_ref(), &[0x8F, 0x3c, 0x01]);
+assert_eq!(get_vu64(&mut buf), Ok(0x0f0f));
+assert_eq!(get_vi64(&mut buf), Ok(-1));
+```
+*/
+use super::signed::zigzag;
+use super::{decode_with_first_and_follow, decode_with_len, decoded_len, encode, Error, MAX_BYTES};
+use ::bytes::{Buf, BufMut, Bytes};
+
+/// Decode the `vu64` value at the front of `buf`, and advance `buf` past it.
+///
+/// On error, `buf` is not changed.
+#[inline]
+pub fn split_vu64(buf: &mut Bytes) -> Result<u64, Error> {
+    let (val, len) = decode_with_len(buf)?;
+    buf.advance(len);
+    Ok(val)
+}
+
+/// Decode the `vu64` value at the cursor of `buf`, and advance `buf` past it.
+///
+/// The value may span multiple non-contiguous chunks of `buf`.
+/// If `buf` has less bytes than the value, `Error::Truncated` is returned,
+/// and `buf` is not advanced. On the other errors, the bytes of the value are consumed.
+pub fn get_vu64<B: Buf>(buf: &mut B) -> Result<u64, Error> {
+    if !buf.has_remaining() {
+        return Err(Error::Truncated);
+    }
+    let byte_1st = buf.chunk()[0];
+    let len = decoded_len(byte_1st) as usize;
+    if buf.remaining() < len {
+        return Err(Error::Truncated);
+    }
+    let mut bytes = [0u8; MAX_BYTES];
+    buf.copy_to_slice(&mut bytes[..len]);
+    decode_with_first_and_follow(len as u8, byte_1st, &bytes[1..len])
+}
+
+/// Encode `value` as `vu64`, and put it to `buf`.
+#[inline]
+pub fn put_vu64<B: BufMut>(buf: &mut B, value: u64) {
+    buf.put_slice(encode(value).as_ref());
+}
+
+/// Decode the zigzag encoded `vu64` value at the cursor of `buf` as a signed integer.
+///
+/// This is the signed counterpart of [`get_vu64()`].
+#[inline]
+pub fn get_vi64<B: Buf>(buf: &mut B) -> Result<i64, Error> {
+    get_vu64(buf).map(zigzag::decode)
+}
+
+/// Zigzag encode `value` as `vu64`, and put it to `buf`.
+#[inline]
+pub fn put_vi64<B: BufMut>(buf: &mut B, value: i64) {
+    put_vu64(buf, zigzag::encode(value));
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_buf {
+    use super::super::{pack, Error};
+    use super::{get_vi64, get_vu64, put_vi64, put_vu64, split_vu64};
+    use bytes::{Buf, Bytes};
+    #[test]
+    fn split_vu64_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX, 1];
+        let mut buf = Bytes::from(pack(&values));
+        for &val in values.iter() {
+            assert_eq!(split_vu64(&mut buf), Ok(val));
+        }
+        assert!(buf.is_empty());
+        assert_eq!(split_vu64(&mut buf), Err(Error::Truncated));
+    }
+    #[test]
+    fn split_vu64_err() {
+        let mut buf = Bytes::from_static(&[0x01, 0xDD, 0x00, 0x00]);
+        assert_eq!(split_vu64(&mut buf), Ok(1));
+        assert_eq!(split_vu64(&mut buf), Err(Error::RedundantEncode));
+        assert_eq!(buf.as_ref(), &[0xDD, 0x00, 0x00]);
+        //
+        let mut buf = Bytes::from_static(&[0x8F]);
+        assert_eq!(split_vu64(&mut buf), Err(Error::Truncated));
+        assert_eq!(buf.len(), 1);
+    }
+    #[test]
+    fn get_put_vu64() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX, 1];
+        let mut buf = bytes::BytesMut::new();
+        for &val in values.iter() {
+            put_vu64(&mut buf, val);
+        }
+        assert_eq!(buf.as_ref(), pack(&values).as_slice());
+        for &val in values.iter() {
+            assert_eq!(get_vu64(&mut buf), Ok(val));
+        }
+        assert_eq!(get_vu64(&mut buf), Err(Error::Truncated));
+    }
+    #[test]
+    fn get_vu64_chunks() {
+        // the values span the two chunks
+        let packed = pack(&[0x0f0f, u64::MAX, 1]);
+        for i in 0..packed.len() {
+            let a = Bytes::copy_from_slice(&packed[..i]);
+            let b = Bytes::copy_from_slice(&packed[i..]);
+            let mut buf = a.chain(b);
+            assert_eq!(get_vu64(&mut buf), Ok(0x0f0f));
+            assert_eq!(get_vu64(&mut buf), Ok(u64::MAX));
+            assert_eq!(get_vu64(&mut buf), Ok(1));
+            assert!(!buf.has_remaining());
+        }
+    }
+    #[test]
+    fn get_vu64_err() {
+        let mut buf = Bytes::from_static(&[0x8F]);
+        assert_eq!(get_vu64(&mut buf), Err(Error::Truncated));
+        assert_eq!(buf.remaining(), 1);
+        //
+        let mut buf = Bytes::from_static(&[0xDD, 0x00, 0x00, 0x01]);
+        assert_eq!(get_vu64(&mut buf), Err(Error::RedundantEncode));
+        assert_eq!(get_vu64(&mut buf), Ok(1));
+    }
+    #[test]
+    fn get_put_vi64() {
+        let values = [0, -1, 1, -64, 64, i64::MIN, i64::MAX];
+        let mut buf = Vec::new();
+        for &val in values.iter() {
+            put_vi64(&mut buf, val);
+        }
+        assert_eq!(buf, crate::signed::pack(&values));
+        let mut buf = buf.as_slice();
+        for &val in values.iter() {
+            assert_eq!(get_vi64(&mut buf), Ok(val));
+        }
+    }
+}
@@ -0,0 +1,110 @@
+/*!
+Supports of the interoperability with other varint formats.
+*/
+
+/// The varint of Protocol Buffers.
+///
+/// This is the LEB128 style varint of the protobuf wire format:
+/// 7 bits per byte from the least significant group, with the MSB of each byte
+/// set when more bytes follow. A `u64` takes 1 to 10 bytes.
+///
+/// Reference: [the __Base 128 Varints__](https://protobuf.dev/programming-guides/encoding/#varints)
+///
+/// # Examples
+///
+/// Transcode a protobuf varint into `vu64`:
+///
+/// ```
+/// use vu64::compat::protobuf;
+/// let (val, len) = protobuf::decode_varint(&[0xAC, 0x02]).unwrap();
+/// assert_eq!((val, len), (300, 2));
+/// assert_eq!(vu64::encode(val).as_ref(), &[0xAC, 0x04]);
+/// ```
+pub mod protobuf {
+    use crate::Error;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    /// Maximum length of a protobuf varint of `u64` in bytes
+    pub const MAX_BYTES: usize = 10;
+
+    /// Decode a protobuf varint at the head of `bytes`.
+    ///
+    /// Returns the value and the consumed length.
+    /// As well as `vu64`, only the shortest encoding is accepted:
+    ///
+    /// - `Error::Truncated`: the last byte has the continuation bit.
+    /// - `Error::RedundantEncode`: the encoding has a trailing zero group.
+    /// - `Error::LeadingOnes`: the value overflows `u64`.
+    pub fn decode_varint(bytes: &[u8]) -> Result<(u64, usize), Error> {
+        let mut val = 0u64;
+        for (i, &byte) in bytes.iter().enumerate().take(MAX_BYTES) {
+            val |= ((byte & 0x7F) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                if i > 0 && byte == 0 {
+                    return Err(Error::RedundantEncode);
+                }
+                if i == MAX_BYTES - 1 && byte > 0x01 {
+                    return Err(Error::LeadingOnes);
+                }
+                return Ok((val, i + 1));
+            }
+        }
+        if bytes.len() < MAX_BYTES {
+            Err(Error::Truncated)
+        } else {
+            Err(Error::LeadingOnes)
+        }
+    }
+
+    /// Encode `value` as a protobuf varint, and append it to `out`.
+    #[cfg(feature = "alloc")]
+    pub fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_protobuf {
+    use super::protobuf::{decode_varint, encode_varint};
+    use crate::Error;
+    #[test]
+    fn protobuf_examples() {
+        let data: [(u64, &[u8]); 6] = [
+            (0, &[0x00]),
+            (1, &[0x01]),
+            (127, &[0x7F]),
+            (150, &[0x96, 0x01]),
+            (300, &[0xAC, 0x02]),
+            (
+                u64::MAX,
+                &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+            ),
+        ];
+        for &(val, bytes) in data.iter() {
+            let mut buf = Vec::new();
+            encode_varint(val, &mut buf);
+            assert_eq!(buf, bytes);
+            assert_eq!(decode_varint(bytes), Ok((val, bytes.len())));
+        }
+    }
+    #[test]
+    fn protobuf_trailing() {
+        assert_eq!(decode_varint(&[0xAC, 0x02, 0x7F]), Ok((300, 2)));
+    }
+    #[test]
+    fn protobuf_err() {
+        assert_eq!(decode_varint(&[]), Err(Error::Truncated));
+        assert_eq!(decode_varint(&[0xAC]), Err(Error::Truncated));
+        assert_eq!(decode_varint(&[0x81, 0x00]), Err(Error::RedundantEncode));
+        assert_eq!(
+            decode_varint(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02]),
+            Err(Error::LeadingOnes)
+        );
+        assert_eq!(decode_varint(&[0x80; 11]), Err(Error::LeadingOnes));
+    }
+}
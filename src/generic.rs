@@ -0,0 +1,192 @@
+/*!
+Experimental: a generalized prefix varint with a configurable group size.
+
+**This module is experimental.** The format and the API may change
+without a major version bump. It is for research and benchmarking of
+alternative layouts, and is not meant for storage.
+
+The value is encoded into `L` groups of `group_bits` bits each.
+The first byte starts with a unary prefix of `L - 1` one bits followed by
+a zero bit, like `vu64`. If the prefix fills the whole first byte, the zero
+bit is omitted. The remaining bits of the first byte hold the least
+significant bits of the value, and the following bytes hold the rest of the
+value in little-endian. The number of groups `L` is the smallest one whose
+payload bits can hold the value.
+
+With `group_bits == 8`, this is exactly the `vu64` format:
+
+```
+use vu64::generic::decode_base;
+# #[cfg(feature = "alloc")]
+# {
+use vu64::generic::encode_base;
+assert_eq!(encode_base(0x0f0f, 8), vu64::encode(0x0f0f).as_ref());
+# }
+assert_eq!(decode_base(&[0x8F, 0x3c], 8), Ok((0x0f0f, 2)));
+```
+
+With `group_bits == 16`, each step of the prefix adds 2 bytes:
+
+```
+# #[cfg(feature = "alloc")]
+# {
+use vu64::generic::{decode_base, encode_base};
+let buf = encode_base(0x0f0f, 16);
+assert_eq!(buf.len(), 2);
+let buf = encode_base(0x0f0f_f0f0, 16);
+assert_eq!(buf.len(), 4);
+assert_eq!(decode_base(&buf, 16), Ok((0x0f0f_f0f0, 4)));
+# }
+```
+*/
+use crate::Error;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// Encode `value` with groups of `group_bits` bits.
+///
+/// # Panics
+///
+/// Panics if `group_bits` is not a multiple of 8 in `8..=64`.
+#[cfg(feature = "alloc")]
+pub fn encode_base(value: u64, group_bits: u8) -> Vec<u8> {
+    let group_bytes = group_bytes(group_bits);
+    let bits = 64 - value.leading_zeros();
+    let mut groups = 1;
+    while payload_bits(groups, group_bytes) < bits {
+        groups += 1;
+    }
+    let length = groups * group_bytes;
+    let first_bits = 8 - prefix_bits(groups);
+    let prefix = !(0xFFu16 >> prefix_ones(groups)) as u8;
+    let mut buf = vec![0u8; length];
+    buf[0] = prefix | (value & ((1u64 << first_bits) - 1)) as u8;
+    let rest = ((value as u128) >> first_bits).to_le_bytes();
+    buf[1..].copy_from_slice(&rest[..length - 1]);
+    buf
+}
+
+/// Decode the value at the head of `bytes` encoded with groups of `group_bits` bits.
+///
+/// Returns the value and the consumed length.
+///
+/// - `Error::Truncated`: `bytes` is shorter than the encoded length.
+/// - `Error::RedundantEncode`: the value fits in fewer groups.
+/// - `Error::LeadingOnes`: the prefix is too long, or the value overflows `u64`.
+///
+/// # Panics
+///
+/// Panics if `group_bits` is not a multiple of 8 in `8..=64`.
+pub fn decode_base(bytes: &[u8], group_bits: u8) -> Result<(u64, usize), Error> {
+    let group_bytes = group_bytes(group_bits);
+    let first = *bytes.first().ok_or(Error::Truncated)?;
+    let ones = first.leading_ones() as usize;
+    let groups = if ones < 8 { ones + 1 } else { 9 };
+    if groups > 1 && payload_bits(groups - 1, group_bytes) >= 64 {
+        return Err(Error::LeadingOnes);
+    }
+    let length = groups * group_bytes;
+    if bytes.len() < length {
+        return Err(Error::Truncated);
+    }
+    let first_bits = 8 - prefix_bits(groups);
+    let mut rest = [0u8; 16];
+    rest[..length - 1].copy_from_slice(&bytes[1..length]);
+    let low = first as u128 & ((1u128 << first_bits) - 1);
+    let val = (u128::from_le_bytes(rest) << first_bits) | low;
+    if val > u64::MAX as u128 {
+        return Err(Error::LeadingOnes);
+    }
+    if groups > 1 && val < 1u128 << payload_bits(groups - 1, group_bytes) {
+        return Err(Error::RedundantEncode);
+    }
+    Ok((val as u64, length))
+}
+
+#[inline]
+fn group_bytes(group_bits: u8) -> usize {
+    assert!(
+        group_bits % 8 == 0 && (8..=64).contains(&group_bits),
+        "group_bits: {} must be a multiple of 8 in 8..=64",
+        group_bits
+    );
+    group_bits as usize / 8
+}
+
+// the number of one bits of the prefix of `groups` groups.
+#[cfg(feature = "alloc")]
+#[inline]
+fn prefix_ones(groups: usize) -> usize {
+    groups - 1
+}
+
+// the number of bits of the prefix, including the terminating zero bit if any.
+#[inline]
+fn prefix_bits(groups: usize) -> usize {
+    groups.min(8)
+}
+
+// the number of payload bits of `groups` groups.
+#[inline]
+fn payload_bits(groups: usize, group_bytes: usize) -> u32 {
+    (groups * group_bytes * 8 - prefix_bits(groups)) as u32
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_generic {
+    use super::{decode_base, encode_base};
+    use crate::Error;
+    const VALUES: [u64; 10] = [
+        0,
+        1,
+        127,
+        128,
+        0x3FFF,
+        0x4000,
+        0x0f0f_f0f0,
+        0x0f0f_f0f0_0f0f,
+        0xFF_FFFF_FFFF_FFFF,
+        u64::MAX,
+    ];
+    #[test]
+    fn group_8_is_vu64() {
+        for &val in VALUES.iter() {
+            let buf = encode_base(val, 8);
+            assert_eq!(buf, crate::encode(val).as_ref());
+            assert_eq!(decode_base(&buf, 8), Ok((val, buf.len())));
+        }
+    }
+    #[test]
+    fn round_trip_wider() {
+        for &group_bits in [16, 32, 64].iter() {
+            for &val in VALUES.iter() {
+                let buf = encode_base(val, group_bits);
+                assert_eq!(buf.len() % (group_bits as usize / 8), 0);
+                assert_eq!(decode_base(&buf, group_bits), Ok((val, buf.len())));
+            }
+        }
+    }
+    #[test]
+    fn lengths_16() {
+        assert_eq!(encode_base(0x7FFF, 16).len(), 2);
+        assert_eq!(encode_base(0x8000, 16).len(), 4);
+        assert_eq!(encode_base(u64::MAX, 16).len(), 10);
+    }
+    #[test]
+    fn decode_base_err() {
+        assert_eq!(decode_base(&[], 16), Err(Error::Truncated));
+        assert_eq!(decode_base(&[0x80, 0x00, 0x00], 16), Err(Error::Truncated));
+        // 1 fits in 1 group
+        assert_eq!(
+            decode_base(&[0x81, 0x00, 0x00, 0x00], 16),
+            Err(Error::RedundantEncode)
+        );
+        // 16-bit groups need at most 5 groups
+        assert_eq!(decode_base(&[0xF8; 16], 16), Err(Error::LeadingOnes));
+    }
+    #[test]
+    #[should_panic]
+    fn encode_base_invalid_group_bits() {
+        encode_base(0, 12);
+    }
+}
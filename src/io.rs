@@ -27,7 +27,7 @@ assert_eq!(crsr.get_ref().as_slice(), vec_0.as_slice());
 ```
 */
 use super::signed::zigzag;
-use super::{decode_with_first_and_follow, decoded_len, encode, MAX_BYTES};
+use super::{decode_with_first_and_follow, decoded_len, encode, Error, Vu64, MAX_BYTES};
 use std::fs::File;
 use std::io::{Cursor, Read, Result, Write};
 
@@ -55,12 +55,88 @@ pub trait ReadVu64: std::io::Read {
         if len > 1 {
             self.read_exact_max8byte(&mut buf[..len as usize - 1])?;
         }
-        match decode_with_first_and_follow(len, byte_1st, &buf[..len as usize - 1]) {
-            Ok(i) => Ok(i),
-            Err(err) => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("{err}"),
-            )),
+        Ok(decode_with_first_and_follow(
+            len,
+            byte_1st,
+            &buf[..len as usize - 1],
+        )?)
+    }
+    /// reads `vu64` bytes and decods it to `u64`, reading at most `*limit` bytes.
+    ///
+    /// `*limit` is decremented by every byte consumed from the reader, also on
+    /// an error, so it always agrees with the stream position. If the value needs
+    /// more bytes than `*limit`, an `InvalidData` error is returned after consuming
+    /// only the first byte, and the follow bytes are not read. If the value is
+    /// invalid, all its bytes are consumed. This prevents a field from reading
+    /// past its message boundary in a framed protocol.
+    ///
+    /// If reading the follow bytes fails, `*limit` is decremented by the number
+    /// of the follow bytes, while the stream position is unspecified as
+    /// with `read_exact()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::io::ReadVu64;
+    /// let mut crsr = std::io::Cursor::new(vec![0x8F, 0x3c, 0x8F, 0x3c]);
+    /// let mut limit = 3;
+    /// assert_eq!(crsr.read_and_decode_vu64_limited(&mut limit).unwrap(), 0x0f0f);
+    /// assert_eq!(limit, 1);
+    /// assert!(crsr.read_and_decode_vu64_limited(&mut limit).is_err());
+    /// assert_eq!(limit, 0);
+    /// ```
+    fn read_and_decode_vu64_limited(&mut self, limit: &mut usize) -> Result<u64> {
+        if *limit == 0 {
+            return Err(limit_error(1, *limit));
+        }
+        let mut buf = [0u8; MAX_BYTES - 1];
+        let byte_1st = self.read_one_byte()?;
+        let len = decoded_len(byte_1st);
+        let follow_len = len as usize - 1;
+        let before = *limit;
+        *limit -= 1;
+        if follow_len > *limit {
+            return Err(limit_error(len as usize, before));
+        }
+        *limit -= follow_len;
+        if follow_len > 0 {
+            self.read_exact_max8byte(&mut buf[..follow_len])?;
+        }
+        Ok(decode_with_first_and_follow(
+            len,
+            byte_1st,
+            &buf[..follow_len],
+        )?)
+    }
+    /// reads `vu64` bytes and decods it to `u64`, and returns it with
+    /// the `Vu64` of the exact bytes read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::io::ReadVu64;
+    /// let mut crsr = std::io::Cursor::new(vec![0x8F, 0x3c, 0x7F]);
+    /// let (val, raw) = crsr.read_and_decode_vu64_raw_bytes().unwrap();
+    /// assert_eq!(val, 0x0f0f);
+    /// assert_eq!(raw.as_ref(), &[0x8F, 0x3c]);
+    /// ```
+    fn read_and_decode_vu64_raw_bytes(&mut self) -> Result<(u64, Vu64)> {
+        let mut raw = Vu64 {
+            length: 0,
+            bytes: [0u8; MAX_BYTES],
+        };
+        let byte_1st = self.read_one_byte()?;
+        let len = decoded_len(byte_1st);
+        raw.bytes[0] = byte_1st;
+        if len > 1 {
+            self.read_exact_max8byte(&mut raw.bytes[1..len as usize])?;
+        }
+        match decode_with_first_and_follow(len, byte_1st, &raw.bytes[1..len as usize]) {
+            Ok(i) => {
+                raw.length = len;
+                Ok((i, raw))
+            }
+            Err(err) => Err(err.into()),
         }
     }
     /// reads `vi64` bytes and decods it to `i64`
@@ -68,6 +144,115 @@ pub trait ReadVu64: std::io::Read {
     fn read_and_decode_vi64(&mut self) -> Result<i64> {
         self.read_and_decode_vu64().map(zigzag::decode)
     }
+    /// returns an iterator that reads `vu64` values until the end of stream,
+    /// and yields each value with the byte offset at which it started.
+    ///
+    /// The offset counts from the current position of `self`. The iterator ends
+    /// at the end of stream on a value boundary, and yields an error and then ends
+    /// if a value is truncated or invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::io::ReadVu64;
+    /// let mut crsr = std::io::Cursor::new(vec![0x01, 0x8F, 0x3c, 0x7F]);
+    /// let v: Vec<(u64, u64)> = crsr.decode_positions().map(|r| r.unwrap()).collect();
+    /// assert_eq!(v, vec![(1, 0), (0x0f0f, 1), (127, 3)]);
+    /// ```
+    #[inline]
+    fn decode_positions(&mut self) -> DecodePositions<'_, Self>
+    where
+        Self: Sized,
+    {
+        DecodePositions {
+            reader: self,
+            pos: 0,
+            done: false,
+        }
+    }
+    /// returns an iterator that reads `vu64` values until the end of stream,
+    /// and yields them in batches of `batch_size` values.
+    ///
+    /// The final batch may be smaller. If a value is truncated or invalid,
+    /// the values read before it are yielded as a batch, and then the error.
+    /// The capacity of a batch grows as values are read, so a huge `batch_size`
+    /// does not allocate up front.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::io::ReadVu64;
+    /// let mut crsr = std::io::Cursor::new(vec![0x01, 0x02, 0x03]);
+    /// let v: Vec<Vec<u64>> = crsr.read_batches(2).map(|r| r.unwrap()).collect();
+    /// assert_eq!(v, vec![vec![1, 2], vec![3]]);
+    /// ```
+    #[inline]
+    fn read_batches(&mut self, batch_size: usize) -> ReadBatches<'_, Self>
+    where
+        Self: Sized,
+    {
+        assert!(batch_size > 0, "batch_size must be greater than 0");
+        ReadBatches {
+            inner: self.decode_positions(),
+            batch_size,
+            pending: None,
+        }
+    }
+}
+
+/// An iterator of `vu64` values and their byte offsets,
+/// returned by [`ReadVu64::decode_positions()`].
+#[derive(Debug)]
+pub struct DecodePositions<'a, R: ReadVu64> {
+    reader: &'a mut R,
+    pos: u64,
+    done: bool,
+}
+
+impl<'a, R: ReadVu64> Iterator for DecodePositions<'a, R> {
+    type Item = Result<(u64, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let byte_1st = match self.reader.read_one_byte() {
+            Ok(byte) => byte,
+            Err(err) => {
+                self.done = true;
+                if err.kind() == std::io::ErrorKind::UnexpectedEof {
+                    return None;
+                }
+                return Some(Err(err));
+            }
+        };
+        let mut buf = [0u8; MAX_BYTES - 1];
+        let len = decoded_len(byte_1st);
+        if len > 1 {
+            if let Err(err) = self
+                .reader
+                .read_exact_max8byte(&mut buf[..len as usize - 1])
+            {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        match decode_with_first_and_follow(len, byte_1st, &buf[..len as usize - 1]) {
+            Ok(val) => {
+                let start = self.pos;
+                self.pos += len as u64;
+                Some(Ok((val, start)))
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err.into()))
+            }
+        }
+    }
 }
 
 /// io write trait of `vu64` and `vi64`
@@ -82,6 +267,247 @@ pub trait WriteVu64: std::io::Write {
     fn encode_and_write_vi64(&mut self, value: i64) -> Result<()> {
         self.encode_and_write_vu64(zigzag::encode(value))
     }
+    /// encods `u64` to `vu64` bytes and writes it, only if it fits in `remaining` bytes.
+    ///
+    /// Returns the number of bytes written. If the encoded length is greater than
+    /// `remaining`, nothing is written and an `InvalidInput` error is returned.
+    /// This prevents overrunning a fixed-size budget, e.g. a packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::io::WriteVu64;
+    /// let mut crsr = std::io::Cursor::new(Vec::new());
+    /// assert_eq!(crsr.try_encode_and_write_vu64(0x0f0f, 2).unwrap(), 2);
+    /// assert!(crsr.try_encode_and_write_vu64(0x0f0f, 1).is_err());
+    /// assert_eq!(crsr.get_ref().as_slice(), &[0x8F, 0x3c]);
+    /// ```
+    fn try_encode_and_write_vu64(&mut self, value: u64, remaining: usize) -> Result<usize> {
+        let encoded = encode(value);
+        let len = encoded.as_ref().len();
+        if len > remaining {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("vu64 needs {len} bytes, but only {remaining} bytes remain"),
+            ));
+        }
+        self.write_all(encoded.as_ref())?;
+        Ok(len)
+    }
+    /// encods `u64` to `vu64` bytes and writes it, and then flushes `self`.
+    ///
+    /// This is for the protocols that require each message to be flushed,
+    /// so a flush on a buffered writer is never forgotten.
+    #[inline]
+    fn write_vu64_and_flush(&mut self, value: u64) -> Result<()> {
+        self.encode_and_write_vu64(value)?;
+        self.flush()
+    }
+}
+
+/// reads `vu64` bytes from `r` and decods it to `u64`.
+///
+/// This is the same as [`ReadVu64::read_and_decode_vu64()`],
+/// but does not need the trait in scope.
+///
+/// # Examples
+///
+/// ```
+/// let mut crsr = std::io::Cursor::new(vec![0x8F, 0x3c]);
+/// assert_eq!(vu64::io::read_vu64(&mut crsr).unwrap(), 0x0f0f);
+/// ```
+pub fn read_vu64<R: Read + ?Sized>(r: &mut R) -> Result<u64> {
+    let mut buf = [0u8; MAX_BYTES];
+    r.read_exact(&mut buf[..1])?;
+    let len = decoded_len(buf[0]);
+    if len > 1 {
+        r.read_exact(&mut buf[1..len as usize])?;
+    }
+    Ok(decode_with_first_and_follow(
+        len,
+        buf[0],
+        &buf[1..len as usize],
+    )?)
+}
+
+/// encods `u64` to `vu64` bytes and writes it to `w`.
+///
+/// This is the same as [`WriteVu64::encode_and_write_vu64()`],
+/// but does not need the trait in scope, and returns the number of bytes written.
+///
+/// # Examples
+///
+/// ```
+/// let mut crsr = std::io::Cursor::new(Vec::new());
+/// assert_eq!(vu64::io::write_vu64(&mut crsr, 0x0f0f).unwrap(), 2);
+/// assert_eq!(crsr.get_ref().as_slice(), &[0x8F, 0x3c]);
+/// ```
+pub fn write_vu64<W: Write + ?Sized>(w: &mut W, value: u64) -> Result<usize> {
+    let encoded = encode(value);
+    w.write_all(encoded.as_ref())?;
+    Ok(encoded.as_ref().len())
+}
+
+/// reads and validates `vu64` values from `r` until the end of stream.
+///
+/// Returns the number of values on success. On the first invalid value,
+/// returns the byte offset at which the value started and the error, so a
+/// corruption can be reported as "at byte N". A value cut off by the end of
+/// stream, or by any other read error, is reported as `Error::Truncated`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{io::validate_stream, Error};
+/// let mut crsr = std::io::Cursor::new(vec![0x01, 0x8F, 0x3c, 0xE0, 0x0f]);
+/// assert_eq!(validate_stream(&mut crsr), Err((3, Error::Truncated)));
+/// ```
+pub fn validate_stream<R: Read>(r: &mut R) -> std::result::Result<usize, (usize, Error)> {
+    let mut count = 0;
+    let mut offset = 0;
+    let mut buf = [0u8; MAX_BYTES];
+    loop {
+        match r.read_exact(&mut buf[..1]) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(count),
+            Err(_) => return Err((offset, Error::Truncated)),
+        }
+        let len = decoded_len(buf[0]);
+        if len > 1 && r.read_exact(&mut buf[1..len as usize]).is_err() {
+            return Err((offset, Error::Truncated));
+        }
+        if let Err(err) = decode_with_first_and_follow(len, buf[0], &buf[1..len as usize]) {
+            return Err((offset, err));
+        }
+        offset += len as usize;
+        count += 1;
+    }
+}
+
+/// A writer adapter that writes `vu64` values to a data stream and
+/// their start offsets to an index stream in a single pass.
+///
+/// Each offset is written as a `vu64` too, so the `i`-th value of the index
+/// stream is the byte offset of the `i`-th value in the data stream.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::io::DualWriter;
+/// let mut w = DualWriter::new(Vec::new(), Vec::new());
+/// w.push(1).unwrap();
+/// w.push(0x0f0f).unwrap();
+/// w.push(127).unwrap();
+/// let (data, index) = w.into_inner();
+/// assert_eq!(data, vec![0x01, 0x8F, 0x3c, 0x7F]);
+/// assert_eq!(index, vec![0, 1, 3]);
+/// ```
+#[derive(Debug)]
+pub struct DualWriter<W1: Write, W2: Write> {
+    data: W1,
+    index: W2,
+    offset: u64,
+    poisoned: bool,
+}
+
+impl<W1: Write, W2: Write> DualWriter<W1, W2> {
+    /// Create a new `DualWriter` writing to `data` and `index`.
+    #[inline]
+    pub fn new(data: W1, index: W2) -> Self {
+        DualWriter {
+            data,
+            index,
+            offset: 0,
+            poisoned: false,
+        }
+    }
+    /// encods `value` and writes it to the data stream, and writes its offset
+    /// to the index stream.
+    ///
+    /// If a write fails, the two streams may be out of step, e.g. the data
+    /// stream has a value without its index entry. So the `DualWriter` is
+    /// poisoned, and the later pushes return an error without writing.
+    pub fn push(&mut self, value: u64) -> Result<()> {
+        if self.poisoned {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "DualWriter is poisoned by a previous write error",
+            ));
+        }
+        let encoded = encode(value);
+        self.poisoned = true;
+        self.data.write_all(encoded.as_ref())?;
+        self.index.write_all(encode(self.offset).as_ref())?;
+        self.poisoned = false;
+        self.offset += encoded.as_ref().len() as u64;
+        Ok(())
+    }
+    /// Returns `true` if a previous [`push()`](DualWriter::push) failed.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+    /// The number of bytes written to the data stream.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    /// Flush both streams.
+    pub fn flush(&mut self) -> Result<()> {
+        self.data.flush()?;
+        self.index.flush()
+    }
+    /// Unwrap this `DualWriter`, returning the data and the index writers.
+    #[inline]
+    pub fn into_inner(self) -> (W1, W2) {
+        (self.data, self.index)
+    }
+}
+
+/// An iterator of batches of `vu64` values,
+/// returned by [`ReadVu64::read_batches()`].
+#[derive(Debug)]
+pub struct ReadBatches<'a, R: ReadVu64> {
+    inner: DecodePositions<'a, R>,
+    batch_size: usize,
+    pending: Option<std::io::Error>,
+}
+
+impl<'a, R: ReadVu64> Iterator for ReadBatches<'a, R> {
+    type Item = Result<Vec<u64>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.pending.take() {
+            return Some(Err(err));
+        }
+        // a huge `batch_size` must not be reserved up front
+        let mut batch = Vec::with_capacity(self.batch_size.min(1024));
+        while batch.len() < self.batch_size {
+            match self.inner.next() {
+                Some(Ok((val, _))) => batch.push(val),
+                Some(Err(err)) => {
+                    if batch.is_empty() {
+                        return Some(Err(err));
+                    }
+                    self.pending = Some(err);
+                    break;
+                }
+                None => break,
+            }
+        }
+        if batch.is_empty() {
+            None
+        } else {
+            Some(Ok(batch))
+        }
+    }
+}
+
+fn limit_error(len: usize, limit: usize) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("vu64 needs {len} bytes, but the limit is {limit} bytes"),
+    )
 }
 
 impl ReadVu64 for File {}
@@ -169,4 +595,308 @@ mod test_io {
         assert!(r.is_ok());
         assert_eq!(r.unwrap(), 0x0f0f_f0f0_0f0f_f0f0);
     }
+    #[test]
+    fn write_vu64_1() {
+        let vec: Vec<u8> = Vec::new();
+        let mut crsr = std::io::Cursor::new(vec);
+        let r = super::write_vu64(&mut crsr, 0x0f0f);
+        assert_eq!(r.unwrap(), 2);
+        let r = super::write_vu64(&mut crsr, 0x0f0f_f0f0_0f0f_f0f0);
+        assert_eq!(r.unwrap(), 9);
+        assert_eq!(
+            crsr.get_ref().as_slice(),
+            &[0x8F, 0x3c, 0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f]
+        );
+    }
+    #[test]
+    fn read_vu64_1() {
+        let vec: Vec<u8> = vec![
+            0x8F, 0x3c, 0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f,
+        ];
+        let mut crsr = std::io::Cursor::new(vec);
+        assert_eq!(super::read_vu64(&mut crsr).unwrap(), 0x0f0f);
+        assert_eq!(super::read_vu64(&mut crsr).unwrap(), 0x0f0f_f0f0_0f0f_f0f0);
+        let r = super::read_vu64(&mut crsr);
+        assert!(r.is_err());
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+    #[test]
+    fn read_vu64_err_redundant() {
+        let vec: Vec<u8> = vec![0xDD, 0, 0];
+        let mut crsr = std::io::Cursor::new(vec);
+        let r = super::read_vu64(&mut crsr);
+        assert!(r.is_err());
+        assert_eq!(
+            format!("{}", r.unwrap_err()),
+            "redundant encoded vu64 value"
+        );
+    }
+    #[test]
+    fn write_read_vu64_slice() {
+        let mut vec: Vec<u8> = Vec::new();
+        for val in [0, 127, 128, u64::MAX] {
+            super::write_vu64(&mut vec, val).unwrap();
+        }
+        let mut slice = vec.as_slice();
+        for val in [0, 127, 128, u64::MAX] {
+            assert_eq!(super::read_vu64(&mut slice).unwrap(), val);
+        }
+        assert!(slice.is_empty());
+    }
+    #[test]
+    fn vu64_decode_positions_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX, 5];
+        let mut vec: Vec<u8> = Vec::new();
+        let mut positions = Vec::new();
+        for &val in values.iter() {
+            positions.push(vec.len() as u64);
+            vec.extend_from_slice(crate::encode(val).as_ref());
+        }
+        let mut crsr = std::io::Cursor::new(vec);
+        let mut count = 0;
+        for (i, r) in crsr.decode_positions().enumerate() {
+            let (val, pos) = r.unwrap();
+            assert_eq!(val, values[i]);
+            assert_eq!(pos, positions[i]);
+            count += 1;
+        }
+        assert_eq!(count, values.len());
+    }
+    #[test]
+    fn vu64_decode_positions_err_truncated() {
+        let vec: Vec<u8> = vec![0x01, 0x8F, 0x3c, 0xE0, 0x0f];
+        let mut crsr = std::io::Cursor::new(vec);
+        let mut iter = crsr.decode_positions();
+        assert_eq!(iter.next().unwrap().unwrap(), (1, 0));
+        assert_eq!(iter.next().unwrap().unwrap(), (0x0f0f, 1));
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert!(iter.next().is_none());
+    }
+    #[test]
+    fn vu64_decode_positions_err_redundant() {
+        let vec: Vec<u8> = vec![0x01, 0xDD, 0x00, 0x00, 0x01];
+        let mut crsr = std::io::Cursor::new(vec);
+        let mut iter = crsr.decode_positions();
+        assert_eq!(iter.next().unwrap().unwrap(), (1, 0));
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(format!("{err}"), "redundant encoded vu64 value");
+        assert!(iter.next().is_none());
+    }
+    #[test]
+    fn vu64_read_and_decode_raw_bytes() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let mut vec: Vec<u8> = Vec::new();
+        for &val in values.iter() {
+            vec.extend_from_slice(crate::encode(val).as_ref());
+        }
+        let mut crsr = std::io::Cursor::new(vec);
+        for &val in values.iter() {
+            let (v, raw) = crsr.read_and_decode_vu64_raw_bytes().unwrap();
+            assert_eq!(v, val);
+            assert_eq!(raw.as_ref(), crate::encode(val).as_ref());
+            assert_eq!(raw, crate::encode(val));
+        }
+        let err = crsr.read_and_decode_vu64_raw_bytes().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+    #[test]
+    fn vu64_read_and_decode_raw_bytes_err() {
+        let mut crsr = std::io::Cursor::new(vec![0xDD, 0x00, 0x00]);
+        let err = crsr.read_and_decode_vu64_raw_bytes().unwrap_err();
+        assert_eq!(format!("{err}"), "redundant encoded vu64 value");
+    }
+    #[test]
+    fn dual_writer_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX, 5];
+        let mut w = super::DualWriter::new(Vec::new(), Vec::new());
+        for &val in values.iter() {
+            w.push(val).unwrap();
+        }
+        w.flush().unwrap();
+        assert_eq!(w.offset(), 25);
+        let (data, index) = w.into_inner();
+        assert_eq!(data.len(), 25);
+        let mut crsr = std::io::Cursor::new(index);
+        for &val in values.iter() {
+            let offset = crsr.read_and_decode_vu64().unwrap() as usize;
+            assert_eq!(crate::decode(&data[offset..]), Ok(val));
+        }
+        assert!(crsr.read_and_decode_vu64().is_err());
+    }
+    #[test]
+    fn dual_writer_poisoned() {
+        // the index stream has room for only 1 entry
+        let mut index = [0u8; 1];
+        let mut w = super::DualWriter::new(Vec::new(), &mut index[..]);
+        w.push(1).unwrap();
+        assert!(!w.is_poisoned());
+        let err = w.push(0x0f0f).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        assert!(w.is_poisoned());
+        assert_eq!(w.offset(), 1);
+        // the later pushes write nothing
+        assert!(w.push(2).is_err());
+        let (data, _) = w.into_inner();
+        assert_eq!(data, vec![0x01, 0x8F, 0x3c]);
+    }
+    #[test]
+    fn vu64_write_and_flush() {
+        struct FlushCounter {
+            buf: Vec<u8>,
+            flushed: usize,
+        }
+        impl std::io::Write for FlushCounter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.buf.write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                self.flushed += 1;
+                Ok(())
+            }
+        }
+        impl WriteVu64 for FlushCounter {}
+        //
+        let mut w = FlushCounter {
+            buf: Vec::new(),
+            flushed: 0,
+        };
+        w.write_vu64_and_flush(0x0f0f).unwrap();
+        assert_eq!(w.buf, vec![0x8F, 0x3c]);
+        assert_eq!(w.flushed, 1);
+    }
+    #[test]
+    fn validate_stream_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let buf = crate::pack(&values);
+        let mut crsr = std::io::Cursor::new(buf);
+        assert_eq!(super::validate_stream(&mut crsr), Ok(values.len()));
+        let mut crsr = std::io::Cursor::new(Vec::new());
+        assert_eq!(super::validate_stream(&mut crsr), Ok(0));
+    }
+    #[test]
+    fn validate_stream_err() {
+        use crate::Error;
+        // valid up to the offset 8, then truncated
+        let mut buf = crate::pack(&[0, 127, 128, 0x0f0f_f0f0]);
+        assert_eq!(buf.len(), 8);
+        buf.extend_from_slice(&crate::encode(u64::MAX).as_ref()[..5]);
+        let mut crsr = std::io::Cursor::new(buf);
+        assert_eq!(
+            super::validate_stream(&mut crsr),
+            Err((8, Error::Truncated))
+        );
+        //
+        let mut crsr = std::io::Cursor::new(vec![0x01, 0x8F, 0x3c, 0xDD, 0x00, 0x00]);
+        assert_eq!(
+            super::validate_stream(&mut crsr),
+            Err((3, Error::RedundantEncode))
+        );
+    }
+    #[test]
+    fn vu64_try_encode_and_write() {
+        let mut crsr = std::io::Cursor::new(Vec::new());
+        assert_eq!(crsr.try_encode_and_write_vu64(127, 1).unwrap(), 1);
+        assert_eq!(crsr.try_encode_and_write_vu64(0x0f0f_f0f0, 10).unwrap(), 4);
+        assert_eq!(crsr.try_encode_and_write_vu64(u64::MAX, 9).unwrap(), 9);
+        assert_eq!(crsr.get_ref().len(), 14);
+        //
+        let err = crsr.try_encode_and_write_vu64(128, 1).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        let err = crsr.try_encode_and_write_vu64(0, 0).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(crsr.get_ref().len(), 14);
+    }
+    #[test]
+    fn vu64_read_and_decode_limited() {
+        // exactly enough
+        let mut crsr = std::io::Cursor::new(vec![0x01, 0x8F, 0x3c, 0xE0, 0x00, 0x00, 0x10]);
+        let mut limit = 7;
+        assert_eq!(crsr.read_and_decode_vu64_limited(&mut limit).unwrap(), 1);
+        assert_eq!(limit, 6);
+        assert_eq!(
+            crsr.read_and_decode_vu64_limited(&mut limit).unwrap(),
+            0x0f0f
+        );
+        assert_eq!(limit, 4);
+        assert_eq!(
+            crsr.read_and_decode_vu64_limited(&mut limit).unwrap(),
+            0x0100_0000
+        );
+        assert_eq!(limit, 0);
+        let err = crsr.read_and_decode_vu64_limited(&mut limit).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // one byte short
+        let mut crsr = std::io::Cursor::new(vec![0xE0, 0x00, 0x00, 0x10, 0x7F]);
+        let mut limit = 3;
+        let err = crsr.read_and_decode_vu64_limited(&mut limit).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // only the first byte is consumed, and the follow bytes are not read
+        assert_eq!(limit, 2);
+        assert_eq!(crsr.position(), 1);
+    }
+    #[test]
+    fn vu64_read_and_decode_limited_invalid() {
+        // the redundant encoding consumes all its bytes
+        let mut crsr = std::io::Cursor::new(vec![0xDD, 0x00, 0x00, 0x7F]);
+        let mut limit = 4;
+        let err = crsr.read_and_decode_vu64_limited(&mut limit).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(limit, 1);
+        assert_eq!(crsr.position(), 3);
+        assert_eq!(crsr.read_and_decode_vu64_limited(&mut limit).unwrap(), 0x7F);
+        assert_eq!(limit, 0);
+    }
+    #[test]
+    fn vu64_read_batches() {
+        let buf = crate::pack(&[1, 2, 0x0f0f, 4, 5, 6, 7]);
+        let mut crsr = std::io::Cursor::new(buf);
+        let v: Vec<Vec<u64>> = crsr.read_batches(3).map(|r| r.unwrap()).collect();
+        assert_eq!(v, vec![vec![1, 2, 0x0f0f], vec![4, 5, 6], vec![7]]);
+        //
+        let mut crsr = std::io::Cursor::new(Vec::new());
+        assert!(crsr.read_batches(3).next().is_none());
+    }
+    #[test]
+    fn vu64_read_batches_truncated() {
+        let mut buf = crate::pack(&[1, 2, 3, 4]);
+        buf.push(0x8F);
+        let mut crsr = std::io::Cursor::new(buf);
+        let mut it = crsr.read_batches(3);
+        assert_eq!(it.next().unwrap().unwrap(), vec![1, 2, 3]);
+        assert_eq!(it.next().unwrap().unwrap(), vec![4]);
+        let err = it.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert!(it.next().is_none());
+    }
+    #[test]
+    fn vu64_read_batches_huge_batch_size() {
+        let mut crsr = std::io::Cursor::new(vec![0x01, 0x02, 0x03]);
+        let v: Vec<Vec<u64>> = crsr.read_batches(usize::MAX).map(|r| r.unwrap()).collect();
+        assert_eq!(v, vec![vec![1, 2, 3]]);
+        //
+        let mut crsr = std::io::Cursor::new(vec![0x01, 0x02, 0x03]);
+        let batch = crsr.read_batches(1 << 30).next().unwrap().unwrap();
+        assert_eq!(batch, vec![1, 2, 3]);
+        assert!(batch.capacity() <= 1024);
+    }
+    #[test]
+    fn vu64_error_kind() {
+        use crate::Error;
+        use std::io::ErrorKind;
+        // 0xDD: 0b1101_1101, the redundant encoding
+        let mut crsr = std::io::Cursor::new(vec![0xDD, 0x00, 0x00]);
+        let err = crsr.read_and_decode_vu64().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(format!("{err}"), "redundant encoded vu64 value");
+        let inner = err.get_ref().unwrap().downcast_ref::<Error>();
+        assert_eq!(inner, Some(&Error::RedundantEncode));
+        //
+        let err: std::io::Error = Error::Truncated.into();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        let err: std::io::Error = Error::LeadingOnes.into();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err: std::io::Error = Error::BufferFull.into();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+    }
 }
@@ -5,7 +5,22 @@ This is a simple and fast encoder/decoder.
 # Features
 
 - integer value length compaction
-- minimum support rustc 1.58.1 (db9d1b20b 2022-01-20)
+- minimum support rustc 1.58.1 (db9d1b20b 2022-01-20),
+  while the tests need rustc 1.75 or later for the `tokio` dev-dependency
+- `no_std` support by disabling the default `std` feature
+
+## cargo features
+
+- `std`: (default) the `std::error::Error` impl. implies `alloc`.
+- `alloc`: the APIs that return or fill a `Vec`.
+- `io`: (default) the `io` module. implies `std`.
+
+The minimum support rustc above is of the default features. Some optional
+features need a newer rustc:
+
+- `tokio`: rustc 1.75, for `impl Future` in the trait methods of the `async_io` module.
+- `bitstream-io`: rustc 1.79, of `bitstream-io` 2.
+- `rkyv`: rustc 1.81, of `rkyv` 0.8.
 
 ## format pattern
 
@@ -52,17 +67,22 @@ assert_eq!(decode(encode(val).as_ref()).unwrap(), val);
 ## Read from buffer and decode
 
 ```
+# #[cfg(feature = "io")]
+# {
 use vu64::io::ReadVu64;
 let vec: Vec<u8> = vec![0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f];
 let mut crsr = std::io::Cursor::new(vec);
 let r = crsr.read_and_decode_vu64();
 assert!(r.is_ok());
 assert_eq!(r.unwrap(), 0x0f0f_f0f0_0f0f_f0f0);
+# }
 ```
 
 ## Encode and write to buffer
 
 ```
+# #[cfg(feature = "io")]
+# {
 use vu64::io::WriteVu64;
 let vec_0: Vec<u8> = vec![0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f];
 let vec: Vec<u8> = Vec::new();
@@ -70,17 +90,54 @@ let mut crsr = std::io::Cursor::new(vec);
 let r = crsr.encode_and_write_vu64(0x0f0f_f0f0_0f0f_f0f0);
 assert!(r.is_ok());
 assert_eq!(crsr.get_ref().as_slice(), vec_0.as_slice());
+# }
 ```
 
 */
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::cmp::Ordering;
 use core::convert::{TryFrom, TryInto};
 use core::fmt::{self, Debug, Display};
+use core::hash::{Hash, Hasher};
+use core::ops::RangeInclusive;
 
+pub mod compat;
+pub mod generic;
 pub mod signed;
+pub mod vu128;
 
 #[cfg(feature = "io")]
 pub mod io;
 
+#[cfg(all(feature = "std", feature = "tokio"))]
+pub mod async_io;
+
+#[cfg(all(feature = "std", feature = "bitstream-io"))]
+pub mod bits;
+
+#[cfg(feature = "bytes")]
+pub mod buf;
+
+#[cfg(all(feature = "std", feature = "rkyv"))]
+mod rkyv_impl;
+
+#[cfg(all(feature = "alloc", feature = "base64"))]
+mod base64_impl;
+
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+#[cfg(feature = "derive")]
+pub use vu64_derive::Vu64Codec;
+
 /// Maximun integer whose length of `vu64` is 1 byte.
 #[allow(dead_code)]
 pub const MAX_LEN1: u64 = 0x7F;
@@ -122,6 +179,7 @@ pub const MAX_BYTES: usize = 9;
 
 /// `vu64`: serialized variable-length 64-bit integers.
 #[derive(Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
 pub struct Vu64 {
     /// Encoded length in bytes
     length: u8,
@@ -166,6 +224,241 @@ impl TryFrom<&[u8]> for Vu64 {
     }
 }
 
+/// Hashes the encoded bytes. Equal values always have identical encodings,
+/// so this is consistent with `PartialEq`.
+impl Hash for Vu64 {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state);
+    }
+}
+
+/// Compares by the decoded value, not by the encoded bytes.
+impl Ord for Vu64 {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value().cmp(&other.value())
+    }
+}
+
+impl PartialOrd for Vu64 {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Vu64 {
+    /// Add `rhs` to the value of `self`, and return the re-encoded `Vu64`.
+    ///
+    /// Returns `None` on overflow of `u64`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::encode;
+    /// assert_eq!(encode(127).checked_add(1), Some(encode(128)));
+    /// assert_eq!(encode(u64::MAX).checked_add(1), None);
+    /// ```
+    #[inline]
+    pub fn checked_add(&self, rhs: u64) -> Option<Vu64> {
+        self.value().checked_add(rhs).map(encode)
+    }
+
+    /// Subtract `rhs` from the value of `self`, and return the re-encoded `Vu64`.
+    ///
+    /// Returns `None` on underflow of `u64`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::encode;
+    /// assert_eq!(encode(128).checked_sub(1), Some(encode(127)));
+    /// assert_eq!(encode(0).checked_sub(1), None);
+    /// ```
+    #[inline]
+    pub fn checked_sub(&self, rhs: u64) -> Option<Vu64> {
+        self.value().checked_sub(rhs).map(encode)
+    }
+
+    /// Returns the encoding of the value of `self` plus one.
+    ///
+    /// Returns `None` if the value is `u64::MAX`. This is for bumping
+    /// a counter stored as `vu64`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::encode;
+    /// assert_eq!(encode(127).incremented(), Some(encode(128)));
+    /// assert_eq!(encode(u64::MAX).incremented(), None);
+    /// ```
+    #[inline]
+    pub fn incremented(&self) -> Option<Vu64> {
+        self.checked_add(1)
+    }
+
+    /// Returns the decoded value as a fixed 8-byte little-endian array.
+    ///
+    /// This is the fixed-width representation of the value, not the encoded bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::encode;
+    /// assert_eq!(encode(0x0f0f).to_u64_le_bytes(), [0x0f, 0x0f, 0, 0, 0, 0, 0, 0]);
+    /// ```
+    #[inline]
+    pub fn to_u64_le_bytes(&self) -> [u8; 8] {
+        self.value().to_le_bytes()
+    }
+
+    /// Encode the value of a fixed 8-byte little-endian array.
+    ///
+    /// This is the inverse of [`to_u64_le_bytes()`](Vu64::to_u64_le_bytes).
+    #[inline]
+    pub fn from_u64_le_bytes(bytes: [u8; 8]) -> Vu64 {
+        encode(u64::from_le_bytes(bytes))
+    }
+
+    /// Build a `Vu64` from the encoded `bytes` without any checks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is empty, or `bytes.len()` is greater than `MAX_BYTES`.
+    ///
+    /// # Safety
+    ///
+    /// `bytes` must be a complete and canonical `vu64`, as written by [`encode()`].
+    /// Otherwise, the methods of the returned `Vu64` may panic or return a wrong value.
+    /// Use [`is_canonical()`](Vu64::is_canonical) to check it afterwards.
+    #[inline]
+    pub unsafe fn from_bytes_unchecked(bytes: &[u8]) -> Vu64 {
+        assert!(!bytes.is_empty(), "empty bytes of Vu64");
+        let mut vu64 = Vu64 {
+            length: bytes.len() as u8,
+            bytes: [0u8; MAX_BYTES],
+        };
+        vu64.bytes[..bytes.len()].copy_from_slice(bytes);
+        vu64
+    }
+
+    /// Returns `true` if the stored bytes are a complete and canonical `vu64`.
+    ///
+    /// This is always `true` for a `Vu64` built by the safe API. It is for
+    /// validating a `Vu64` built by [`from_bytes_unchecked()`](Vu64::from_bytes_unchecked)
+    /// or through FFI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::{encode, Vu64};
+    /// assert!(encode(0x0f0f).is_canonical());
+    /// let vu64 = unsafe { Vu64::from_bytes_unchecked(&[0x80, 0x01]) };
+    /// assert!(!vu64.is_canonical());
+    /// ```
+    pub fn is_canonical(&self) -> bool {
+        let length = self.length as usize;
+        if length == 0 || length > MAX_BYTES {
+            return false;
+        }
+        match decode(self.as_ref()) {
+            Ok(val) => encoded_len(val) == self.length,
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the decoded value.
+    ///
+    /// This never fails for a `Vu64` built by the safe API, e.g. [`encode()`],
+    /// so the truncation and the redundancy checks are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::encode;
+    /// assert_eq!(encode(0x0f0f).value(), 0x0f0f);
+    /// ```
+    #[inline]
+    pub fn value(&self) -> u64 {
+        // SAFETY: `self.bytes` is always `MAX_BYTES` long, so it has enough bytes
+        // for any length decoded from its first byte.
+        unsafe { decode_value_with_length(decoded_len(self.bytes[0]), &self.bytes) }
+    }
+
+    /// Returns the zigzag decoded value as a signed integer.
+    ///
+    /// This never fails for a `Vu64` built by the safe API, e.g. [`signed::encode()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::signed;
+    /// assert_eq!(signed::encode(-64).signed_value(), -64);
+    /// ```
+    #[inline]
+    pub fn signed_value(&self) -> i64 {
+        signed::zigzag::decode(self.value())
+    }
+
+    /// Encode `value` into `self` in place.
+    ///
+    /// This reuses a `Vu64` across a loop, the same as `*self = encode(value)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut vu64 = vu64::encode(0);
+    /// vu64.set(0x0f0f);
+    /// assert_eq!(vu64.as_ref(), &[0x8F, 0x3c]);
+    /// ```
+    #[inline]
+    pub fn set(&mut self, value: u64) {
+        *self = encode(value);
+    }
+
+    /// Zigzag encode `value` into `self` in place.
+    ///
+    /// This is the signed counterpart of [`set()`](Vu64::set).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut vu64 = vu64::encode(0);
+    /// vu64.set_signed(-64);
+    /// assert_eq!(vu64::signed::decode(vu64.as_ref()), Ok(-64));
+    /// ```
+    #[inline]
+    pub fn set_signed(&mut self, value: i64) {
+        *self = signed::encode(value);
+    }
+
+    /// Returns the encoded length in bytes.
+    ///
+    /// This is the same as `self.as_ref().len()`, and is usable in a const context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::{encode_const, Vu64};
+    /// const V: Vu64 = encode_const(0x0f0f);
+    /// let buf = [0u8; V.len()];
+    /// assert_eq!(buf.len(), 2);
+    /// ```
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.length as usize
+    }
+
+    /// Returns `true` if the encoded length is zero.
+    ///
+    /// A `Vu64` has at least 1 byte, so this is always `false`.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
 /// Get the length of an encoded `vu64` for the given value in bytes.
 #[inline]
 pub fn encoded_len(value: u64) -> u8 {
@@ -194,15 +487,206 @@ static ENCODED_LEN_TBL: [u8; 65] = [
     1,
 ];
 
+/// Get the ratio of the encoded length of `value` to the 8 bytes of a fixed `u64`.
+///
+/// This is `encoded_len(value) as f32 / 8.0`. A ratio greater than `1.0`,
+/// for a 9-byte value, means the encoding expands the value.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::compression_ratio;
+/// assert_eq!(compression_ratio(1), 0.125);
+/// assert_eq!(compression_ratio(u64::MAX), 1.125);
+/// ```
+#[inline]
+pub fn compression_ratio(value: u64) -> f32 {
+    encoded_len(value) as f32 / 8.0
+}
+
+/// Get the length of an encoded `vu64` for the given value in bytes, in const context.
+///
+/// This returns the same as [`encoded_len()`], but is computed from the number of
+/// significant bits instead of the table, so it can be used to validate schema
+/// constants at compile time.
+///
+/// # Examples
+///
+/// ```
+/// const MY_MAX: u64 = 0x1F_FFFF;
+/// const _: () = assert!(vu64::encoded_len_const(MY_MAX) <= 3);
+/// ```
+///
+/// A field wider than expected fails to compile:
+///
+/// ```compile_fail
+/// const MY_MAX: u64 = 0x20_0000;
+/// const _: () = assert!(vu64::encoded_len_const(MY_MAX) <= 3);
+/// ```
+#[inline]
+pub const fn encoded_len_const(value: u64) -> u8 {
+    let bits = 64 - value.leading_zeros();
+    if bits == 0 {
+        1
+    } else if bits > 56 {
+        9
+    } else {
+        ((bits + 6) / 7) as u8
+    }
+}
+
+/// Encode an unsigned 64-bit integer known to be at most `MAX` as `vu64`.
+///
+/// The encoded length is at most `encoded_len_const(MAX)` bytes.
+/// In debug builds, it panics if `value > MAX`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{encode_bounded, MAX_LEN2};
+/// assert_eq!(encode_bounded::<MAX_LEN2>(0x0f0f).as_ref(), &[0x8F, 0x3c]);
+/// ```
+#[inline]
+pub fn encode_bounded<const MAX: u64>(value: u64) -> Vu64 {
+    debug_assert!(value <= MAX, "value: {} <= MAX: {}", value, MAX);
+    encode(value)
+}
+
+/// Encode an unsigned 64-bit integer as `vu64` in a const context.
+///
+/// The result is bit-identical to [`encode()`], so a static table of
+/// encoded integers can be built at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{encode_const, Vu64};
+/// const HEADER: Vu64 = encode_const(0x1234);
+/// assert_eq!(HEADER, vu64::encode(0x1234));
+/// ```
+pub const fn encode_const(value: u64) -> Vu64 {
+    let mut bytes = [0u8; MAX_BYTES];
+    let length = encoded_len_const(value);
+    let follow_len = length - 1;
+    //
+    if follow_len == 0 {
+        // 1-byte special case
+        bytes[0] = value as u8;
+    } else if follow_len < 7 {
+        let encoded = (value << length as u64).to_le_bytes();
+        let mut i = 0;
+        while i < 8 {
+            bytes[i] = encoded[i];
+            i += 1;
+        }
+        bytes[0] = !((!(bytes[0] >> 1)) >> follow_len);
+    } else {
+        let encoded = value.to_le_bytes();
+        let mut i = 0;
+        while i < 8 {
+            bytes[i + 1] = encoded[i];
+            i += 1;
+        }
+        // 8-byte or 9-byte special case
+        bytes[0] = if follow_len == 7 { 0xFE } else { 0xFF };
+    }
+    //
+    Vu64 { bytes, length }
+}
+
+/// Get the number of the leading one bits of the first byte of a `vu64`.
+///
+/// This is the length prefix of the format, the number of the follow bytes,
+/// so `decoded_len(first_byte) == prefix_ones(first_byte) as u8 + 1` always holds.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decoded_len, prefix_ones};
+/// assert_eq!(prefix_ones(0b1101_0000), 2);
+/// assert_eq!(decoded_len(0b1101_0000), 3);
+/// ```
+#[inline]
+pub fn prefix_ones(first_byte: u8) -> u32 {
+    first_byte.leading_ones()
+}
+
 /// Get the length of a `vu64` from the first byte.
 ///
 /// NOTE: The returned value is inclusive of the first byte itself.
+/// It is [`prefix_ones(byte)`](prefix_ones) `+ 1`.
 #[inline]
 pub fn decoded_len(byte: u8) -> u8 {
     byte.leading_ones() as u8 + 1
 }
 
+/// Get the total number of bytes of a `vu64` from the first byte, as `usize`.
+///
+/// The returned value is inclusive of the first byte itself, so after reading
+/// the first byte, the reader needs `needed_bytes(first_byte) - 1` more bytes.
+/// It is [`decoded_len(first_byte)`](decoded_len) as `usize`, and is at most [`MAX_BYTES`].
+///
+/// This does not validate the value; the follow bytes may still be a redundant encoding.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{needed_bytes, MAX_BYTES};
+/// assert_eq!(needed_bytes(0x7F), 1);
+/// assert_eq!(needed_bytes(0x8F), 2);
+/// assert_eq!(needed_bytes(0xFF), MAX_BYTES);
+/// ```
+#[inline]
+pub fn needed_bytes(first_byte: u8) -> usize {
+    decoded_len(first_byte) as usize
+}
+
+/// The table of the length of a `vu64` indexed by the first byte.
+///
+/// `DECODED_LEN_TBL[byte]` is the same as [`decoded_len(byte)`](decoded_len),
+/// for the targets without a fast count of the leading ones.
+#[cfg(feature = "tables")]
+#[rustfmt::skip]
+pub static DECODED_LEN_TBL: [u8; 256] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3,
+    4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+    5, 5, 5, 5, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 8, 9,
+];
+
+/// Get the length of a `vu64` from the first byte, by [`DECODED_LEN_TBL`].
+///
+/// This returns the same as [`decoded_len()`], but is a branchless table
+/// lookup without a count of the leading ones.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(vu64::decoded_len_tbl(0x8F), 2);
+/// ```
+#[cfg(feature = "tables")]
+#[inline]
+pub fn decoded_len_tbl(byte: u8) -> u8 {
+    DECODED_LEN_TBL[byte as usize]
+}
+
 /// Encode an unsigned 64-bit integer as `vu64`.
+///
+/// With the `paranoid` feature, the encoded bytes are decoded again and
+/// compared with `value`, and it panics on a mismatch. This is for validation
+/// environments, and costs nothing without the feature.
 pub fn encode(value: u64) -> Vu64 {
     let mut bytes = [0u8; MAX_BYTES];
     let length = encoded_len(value);
@@ -232,14 +716,102 @@ pub fn encode(value: u64) -> Vu64 {
         }
     }
     //
-    Vu64 { bytes, length }
+    let vu64 = Vu64 { bytes, length };
+    #[cfg(feature = "paranoid")]
+    paranoid_check(value, &vu64);
+    vu64
+}
+
+/// Encode an unsigned 64-bit integer as `vu64` directly into `out`.
+///
+/// Returns the number of bytes written. The written bytes are identical to
+/// `encode(value).as_ref()`, without building an intermediate `Vu64`.
+/// If `out.len()` is smaller than `encoded_len(value)`, `Error::BufferFull`
+/// is returned, and nothing is written.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::encode_to_slice;
+/// let mut buf = [0u8; 4];
+/// assert_eq!(encode_to_slice(0x0f0f, &mut buf), Ok(2));
+/// assert_eq!(&buf[..2], &[0x8F, 0x3c]);
+/// ```
+#[inline]
+pub fn encode_to_slice(value: u64, out: &mut [u8]) -> Result<usize, Error> {
+    let length = encoded_len(value);
+    let len = length as usize;
+    if out.len() < len {
+        return Err(Error::BufferFull);
+    }
+    let follow_len = length - 1;
+    //
+    if follow_len == 0 {
+        // 1-byte special case
+        out[0] = value as u8;
+    } else if follow_len < 7 {
+        let encoded = value << length as u64;
+        out[..len].copy_from_slice(&encoded.to_le_bytes()[..len]);
+        let b1st = out[0];
+        out[0] = !((!(b1st >> 1)) >> follow_len);
+    } else {
+        out[1..len].copy_from_slice(&value.to_le_bytes()[..len - 1]);
+        // 8-byte or 9-byte special case
+        out[0] = if follow_len == 7 { 0xFE } else { 0xFF };
+    }
+    Ok(len)
+}
+
+/// Encode an unsigned 64-bit integer as `vu64`, and feed each encoded byte to `write_byte` in order.
+///
+/// The fed bytes are identical to `encode(value).as_ref()`. This needs neither
+/// a `Write` trait nor an allocation, e.g. to push bytes into a ring buffer one at a time.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::encode_into;
+/// let mut buf = [0u8; 4];
+/// let mut pos = 0;
+/// encode_into(0x0f0f, |b| {
+///     buf[pos] = b;
+///     pos += 1;
+/// });
+/// assert_eq!(&buf[..pos], &[0x8F, 0x3c]);
+/// ```
+#[inline]
+pub fn encode_into(value: u64, mut write_byte: impl FnMut(u8)) {
+    for &b in encode(value).as_ref() {
+        write_byte(b);
+    }
+}
+
+// verify that `encoded` decodes back to `value` with the minimal length.
+// this is only for the `paranoid` feature, and panics on a mismatch.
+#[cfg(feature = "paranoid")]
+#[inline(never)]
+fn paranoid_check(value: u64, encoded: &Vu64) {
+    assert_eq!(
+        encoded.length,
+        encoded_len_const(value),
+        "paranoid: encoded length of {} is wrong: {:?}",
+        value,
+        encoded.as_ref()
+    );
+    assert_eq!(
+        decode(encoded.as_ref()),
+        Ok(value),
+        "paranoid: encoded bytes of {} do not decode back: {:?}",
+        value,
+        encoded.as_ref()
+    );
 }
 
 /// Decode `vu64`-encoded bytes to unsigned 64-bit integer.
 ///
-/// Accepts a mutable reference to a slice containing the `vu64`.
-/// Upon success, the reference is updated to begin at the byte immediately
-/// after the encoded `vu64`.
+/// Accepts a slice starting with the `vu64`. The bytes after the `vu64` are ignored.
+/// To advance through concatenated values, use [`decode_advance()`]
+/// or [`decode_with_len()`].
 #[inline]
 pub fn decode(bytes: &[u8]) -> Result<u64, Error> {
     if bytes.is_empty() {
@@ -252,9 +824,9 @@ pub fn decode(bytes: &[u8]) -> Result<u64, Error> {
 
 /// Decode `vu64`-encoded bytes to unsigned 64-bit integer.
 ///
-/// Accepts a mutable reference to a slice containing the `vu64`.
-/// Upon success, the reference is updated to begin at the byte immediately
-/// after the encoded `vu64`.
+/// Accepts the first byte and a slice of the follow bytes of the `vu64`.
+/// Only the first `decoded_len(first_byte) - 1` bytes of `follow_bytes` are read,
+/// and the trailing bytes are ignored. Use [`decode2_exact()`] to reject them.
 #[inline]
 pub fn decode2(first_byte: u8, follow_bytes: &[u8]) -> Result<u64, Error> {
     let length = decoded_len(first_byte);
@@ -264,9 +836,32 @@ pub fn decode2(first_byte: u8, follow_bytes: &[u8]) -> Result<u64, Error> {
 
 /// Decode `vu64`-encoded bytes to unsigned 64-bit integer.
 ///
-/// Accepts a mutable reference to a slice containing the `vu64`.
-/// Upon success, the reference is updated to begin at the byte immediately
-/// after the encoded `vu64`.
+/// This is the same as [`decode2()`], but `follow_bytes` must have exactly
+/// `decoded_len(first_byte) - 1` bytes. If it is shorter, `Error::Truncated` is
+/// returned, and if it is longer, `Error::TrailingBytes` is returned.
+/// This helps to catch framing bugs of callers who split buffers.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode2_exact, Error};
+/// assert_eq!(decode2_exact(0x8F, &[0x3c]), Ok(0x0f0f));
+/// assert_eq!(decode2_exact(0x8F, &[0x3c, 0x00]), Err(Error::TrailingBytes));
+/// ```
+#[inline]
+pub fn decode2_exact(first_byte: u8, follow_bytes: &[u8]) -> Result<u64, Error> {
+    let length = decoded_len(first_byte);
+    if follow_bytes.len() > length as usize - 1 {
+        return Err(Error::TrailingBytes);
+    }
+    decode_with_first_and_follow(length, first_byte, follow_bytes)
+}
+
+/// Decode `vu64`-encoded bytes to unsigned 64-bit integer.
+///
+/// Accepts the first byte and the follow bytes of the `vu64` packed into
+/// a little-endian `u64`. Only the follow bytes of the value must be set,
+/// and the rest must be zero.
 #[inline]
 pub fn decode3(first_byte: u8, follow_le_max_8_bytes: u64) -> Result<u64, Error> {
     let length = decoded_len(first_byte);
@@ -274,6 +869,109 @@ pub fn decode3(first_byte: u8, follow_le_max_8_bytes: u64) -> Result<u64, Error>
     Ok(result)
 }
 
+/// Decode a 1-byte `vu64` to unsigned 64-bit integer.
+///
+/// This is the fast path for small values (`< 0x80`), which returns `byte` as it is.
+/// If the high bit of `byte` is set, the value has follow bytes, and
+/// `Error::Truncated` is returned, so the caller should use [`decode()`] instead.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode, decode_small};
+/// let buf = [0x8F, 0x3c];
+/// let val = match decode_small(buf[0]) {
+///     Ok(val) => val,
+///     Err(_) => decode(&buf).unwrap(),
+/// };
+/// assert_eq!(val, 0x0f0f);
+/// ```
+#[inline]
+pub fn decode_small(byte: u8) -> Result<u64, Error> {
+    if byte < 0x80 {
+        Ok(byte as u64)
+    } else {
+        Err(Error::Truncated)
+    }
+}
+
+/// Decode a `vu64` at the head of a buffer padded to `MAX_BYTES` bytes.
+///
+/// Since the array always has enough bytes for any `vu64`, the truncation
+/// checks are skipped and only the redundant encoding is checked.
+/// This suits fixed-record layouts where each field is padded to 9 bytes.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_padded9;
+/// let buf = [0x8F, 0x3c, 0, 0, 0, 0, 0, 0, 0];
+/// assert_eq!(decode_padded9(&buf), Ok(0x0f0f));
+/// ```
+#[inline]
+pub fn decode_padded9(bytes: &[u8; MAX_BYTES]) -> Result<u64, Error> {
+    let length = decoded_len(bytes[0]);
+    // SAFETY: `bytes` has `MAX_BYTES` bytes, which is enough for any `length`.
+    let result = unsafe { decode_value_with_length(length, bytes) };
+    if length == 1 || result >= (1 << (7 * (length - 1))) {
+        Ok(result)
+    } else {
+        Err(Error::RedundantEncode)
+    }
+}
+
+/// Decode `vu64`-encoded bytes to unsigned 64-bit integer, and advance `*bytes` past it.
+///
+/// On success, `*bytes` is resliced to start just after the encoded `vu64`.
+/// On error, `*bytes` is not changed, so the caller can retry after
+/// buffering more data.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_advance, Error};
+/// let mut buf: &[u8] = &[0x01, 0x8F, 0x3c, 0xE0];
+/// assert_eq!(decode_advance(&mut buf), Ok(1));
+/// assert_eq!(decode_advance(&mut buf), Ok(0x0f0f));
+/// assert_eq!(decode_advance(&mut buf), Err(Error::Truncated));
+/// assert_eq!(buf, &[0xE0]);
+/// ```
+#[inline]
+pub fn decode_advance(bytes: &mut &[u8]) -> Result<u64, Error> {
+    let (val, len) = decode_with_len(bytes)?;
+    *bytes = &bytes[len..];
+    Ok(val)
+}
+
+/// Decode `vu64`-encoded bytes to unsigned 64-bit integer, and return it with the consumed length.
+///
+/// This validates the truncation and the redundant encoding exactly like [`decode()`].
+/// On success, the returned length equals `decoded_len(bytes[0])`, so a buffer of
+/// concatenated values can be iterated without inspecting the first byte again.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_with_len;
+/// let mut buf: &[u8] = &[0x01, 0x8F, 0x3c];
+/// let mut values = Vec::new();
+/// while !buf.is_empty() {
+///     let (val, len) = decode_with_len(buf).unwrap();
+///     values.push(val);
+///     buf = &buf[len..];
+/// }
+/// assert_eq!(values, vec![1, 0x0f0f]);
+/// ```
+#[inline]
+pub fn decode_with_len(bytes: &[u8]) -> Result<(u64, usize), Error> {
+    if bytes.is_empty() {
+        return Err(Error::Truncated);
+    }
+    let length = decoded_len(bytes[0]);
+    let result = decode_with_length(length, bytes)?;
+    Ok((result, length as usize))
+}
+
 #[inline]
 pub fn check_result_with_length(length: u8, result: u64) -> Result<u64, Error> {
     if length == 1 || result >= (1 << (7 * (length - 1))) {
@@ -287,9 +985,24 @@ pub fn decode_with_length(length: u8, bytes: &[u8]) -> Result<u64, Error> {
     if bytes.len() < length as usize {
         return Err(Error::Truncated);
     }
+    // SAFETY: `bytes` has at least `length` bytes, checked above.
+    let result = unsafe { decode_value_with_length(length, bytes) };
+    // check of the redundant encoding
+    if length == 1 || result >= (1 << (7 * (length - 1))) {
+        Ok(result)
+    } else {
+        Err(Error::RedundantEncode)
+    }
+}
+
+// decode the value of `length` bytes without the truncation and the redundancy checks.
+//
+// Safety: `length` must be in `1..=MAX_BYTES`, and `bytes` must have at least `length` bytes.
+#[inline]
+unsafe fn decode_value_with_length(length: u8, bytes: &[u8]) -> u64 {
     let follow_len = length - 1;
     //
-    let result = if follow_len == 0 {
+    if follow_len == 0 {
         // 1-byte special case
         #[cfg(feature = "vu64_debug")]
         let val = bytes[0] as u64;
@@ -337,12 +1050,6 @@ pub fn decode_with_length(length: u8, bytes: &[u8]) -> Result<u64, Error> {
         unsafe {
             core::hint::unreachable_unchecked()
         }
-    };
-    // check of the redundant encoding
-    if length == 1 || result >= (1 << (7 * (length - 1))) {
-        Ok(result)
-    } else {
-        Err(Error::RedundantEncode)
     }
 }
 
@@ -426,826 +1133,5133 @@ pub fn decode_with_first_and_follow_le(
     }
 }
 
-/// Error type
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub enum Error {
-    /// Value contains unnecessary leading ones
-    LeadingOnes,
+/// The varint format guessed by [`detect_format()`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FormatGuess {
+    /// The bytes are a sequence of canonical `vu64` values.
+    Vu64,
+    /// The bytes are a sequence of canonical [`vint64`](https://crates.io/crates/vint64) values.
+    Vint64,
+    /// The bytes are a sequence of canonical LEB128 (protobuf style) values.
+    Leb128,
+    /// The bytes do not parse cleanly with any known format.
+    Unknown,
+}
 
-    /// Value is truncated / malformed
-    Truncated,
+/// Guess the varint format of `bytes` heuristically.
+///
+/// The whole buffer is parsed as a sequence of values with each format,
+/// and the first format that consumes it exactly with canonical encodings
+/// is returned, in the order `Vu64`, `Vint64`, `Leb128`.
+///
+/// NOTE: This is best-effort. Many short buffers are valid in several formats
+/// (e.g. any run of bytes `< 0x80` is valid `vu64` and LEB128),
+/// so the result is only a hint for migration tools.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{detect_format, FormatGuess};
+/// assert_eq!(detect_format(&[0x8F, 0x3c]), FormatGuess::Vu64);
+/// assert_eq!(detect_format(&[0xFF]), FormatGuess::Vint64);
+/// assert_eq!(detect_format(&[0x80, 0x01]), FormatGuess::Leb128);
+/// ```
+pub fn detect_format(bytes: &[u8]) -> FormatGuess {
+    if bytes.is_empty() {
+        return FormatGuess::Unknown;
+    }
+    if parses_cleanly(bytes, |b| decode(b).map(|v| encoded_len(v) as usize)) {
+        FormatGuess::Vu64
+    } else if parses_cleanly(bytes, detect_vint64_len) {
+        FormatGuess::Vint64
+    } else if parses_cleanly(bytes, detect_leb128_len) {
+        FormatGuess::Leb128
+    } else {
+        FormatGuess::Unknown
+    }
+}
 
-    /// Value is the redundant encoding
-    RedundantEncode,
+fn parses_cleanly<F>(mut bytes: &[u8], f: F) -> bool
+where
+    F: Fn(&[u8]) -> Result<usize, Error>,
+{
+    while !bytes.is_empty() {
+        match f(bytes) {
+            Ok(len) => bytes = &bytes[len..],
+            Err(_) => return false,
+        }
+    }
+    true
 }
 
-impl Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(match self {
-            Error::LeadingOnes => "leading ones in vu64 value",
-            Error::Truncated => "truncated vu64 value",
-            Error::RedundantEncode => "redundant encoded vu64 value",
-        })
+// the length of a canonical `vint64` value at the head of `bytes`.
+fn detect_vint64_len(bytes: &[u8]) -> Result<usize, Error> {
+    let length = if bytes[0] == 0 {
+        MAX_BYTES
+    } else {
+        bytes[0].trailing_zeros() as usize + 1
+    };
+    if bytes.len() < length {
+        return Err(Error::Truncated);
+    }
+    let value = if length == MAX_BYTES {
+        u64::from_le_bytes(bytes[1..9].try_into().unwrap())
+    } else {
+        let mut buf = [0u8; 8];
+        buf[..length].copy_from_slice(&bytes[..length]);
+        u64::from_le_bytes(buf) >> length
+    };
+    if length == 1 || value >= (1 << (7 * (length - 1))) {
+        Ok(length)
+    } else {
+        Err(Error::RedundantEncode)
     }
 }
 
+// the length of a canonical LEB128 value at the head of `bytes`.
+fn detect_leb128_len(bytes: &[u8]) -> Result<usize, Error> {
+    compat::protobuf::decode_varint(bytes).map(|(_, len)| len)
+}
+
+/// Merge two sorted `vu64` sequences into `out`.
+///
+/// `a` and `b` are buffers of concatenated `vu64` values, each sorted in
+/// ascending order. The values are decoded one by one from both heads and the
+/// smaller one is appended to `out`, so neither buffer is fully decoded first.
+/// On ties, the value from `a` comes first.
+///
+/// Because every value is canonically encoded, the encoded bytes are copied
+/// as they are without re-encoding.
+///
+/// NOTE: The inputs are not checked to be sorted; if they are not,
+/// the output is not sorted either.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{encode, merge_sorted};
+/// let mut a = Vec::new();
+/// for val in [1, 300, 70000] {
+///     a.extend_from_slice(encode(val).as_ref());
+/// }
+/// let mut b = Vec::new();
+/// for val in [2, 200] {
+///     b.extend_from_slice(encode(val).as_ref());
+/// }
+/// let mut out = Vec::new();
+/// merge_sorted(&a, &b, &mut out).unwrap();
+/// let mut expected = Vec::new();
+/// for val in [1, 2, 200, 300, 70000] {
+///     expected.extend_from_slice(encode(val).as_ref());
+/// }
+/// assert_eq!(out, expected);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn merge_sorted(mut a: &[u8], mut b: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let head = |bytes: &[u8]| -> Result<Option<(u64, usize)>, Error> {
+        if bytes.is_empty() {
+            Ok(None)
+        } else {
+            decode_with_len(bytes).map(Some)
+        }
+    };
+    let mut head_a = head(a)?;
+    let mut head_b = head(b)?;
+    loop {
+        let take_a = match (head_a, head_b) {
+            (Some((val_a, _)), Some((val_b, _))) => val_a <= val_b,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_a {
+            let (_, len) = head_a.unwrap();
+            out.extend_from_slice(&a[..len]);
+            a = &a[len..];
+            head_a = head(a)?;
+        } else {
+            let (_, len) = head_b.unwrap();
+            out.extend_from_slice(&b[..len]);
+            b = &b[len..];
+            head_b = head(b)?;
+        }
+    }
+    Ok(())
+}
+
+/// Decode a zero-terminated sequence of `vu64` values into `out`.
+///
+/// The values are decoded until an encoded `0` (the single byte `0x00`) is found,
+/// and the consumed length including the terminator is returned.
+/// Since `0` is always encoded as `0x00` and no other encoding starts with it,
+/// the terminator is unambiguous.
+///
+/// NOTE: An actual value `0` can not appear in the body of the sequence.
+/// If `bytes` ends without the terminator, `Error::Truncated` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_until_zero;
+/// let mut values = Vec::new();
+/// let r = decode_until_zero(&[0x01, 0x8F, 0x3c, 0x00, 0x7F], &mut values);
+/// assert_eq!(r, Ok(4));
+/// assert_eq!(values, vec![1, 0x0f0f]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_until_zero(bytes: &[u8], out: &mut Vec<u64>) -> Result<usize, Error> {
+    let mut pos = 0;
+    loop {
+        let (val, len) = decode_with_len(&bytes[pos..])?;
+        pos += len;
+        if val == 0 {
+            return Ok(pos);
+        }
+        out.push(val);
+    }
+}
+
+/// Decode a block of `count` values, each of exactly `len` bytes, into `out`.
+///
+/// This is for columnar formats that store every value of a block with the same
+/// byte width, and returns the consumed length `len * count`.
+/// The length derivation from the first byte of each value is checked only against `len`.
+///
+/// If `bytes` is shorter than `len * count`, or `len * count` overflows `usize`,
+/// `Error::Truncated` is returned and nothing is pushed to `out`. If a value
+/// does not have the prefix of `len` bytes, `Error::LeadingOnes` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_fixed_len_block;
+/// let mut values = Vec::new();
+/// let r = decode_fixed_len_block(&[0x8F, 0x3c, 0x80, 0x02], 2, 2, &mut values);
+/// assert_eq!(r, Ok(4));
+/// assert_eq!(values, vec![0x0f0f, 128]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_fixed_len_block(
+    bytes: &[u8],
+    len: u8,
+    count: usize,
+    out: &mut Vec<u64>,
+) -> Result<usize, Error> {
+    let total = (len as usize).checked_mul(count).ok_or(Error::Truncated)?;
+    if bytes.len() < total {
+        return Err(Error::Truncated);
+    }
+    if count == 0 {
+        return Ok(0);
+    }
+    if len == 0 || len as usize > MAX_BYTES {
+        return Err(Error::LeadingOnes);
+    }
+    out.reserve(count);
+    for chunk in bytes[..total].chunks_exact(len as usize) {
+        if decoded_len(chunk[0]) != len {
+            return Err(Error::LeadingOnes);
+        }
+        out.push(decode_with_length(len, chunk)?);
+    }
+    Ok(total)
+}
+
+/// Decode every `vu64` value of `bytes` into a `Vu64` and push it to `out`.
+///
+/// Unlike decoding into `u64`, each `Vu64` keeps the encoded bytes,
+/// so they can be inspected or re-serialized as they are.
+/// Returns the number of values pushed.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_to_vu64s;
+/// let mut out = Vec::new();
+/// assert_eq!(decode_to_vu64s(&[0x01, 0x8F, 0x3c], &mut out), Ok(2));
+/// assert_eq!(out[1].as_ref(), &[0x8F, 0x3c]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_to_vu64s(mut bytes: &[u8], out: &mut Vec<Vu64>) -> Result<usize, Error> {
+    let mut count = 0;
+    while !bytes.is_empty() {
+        let (_, len) = decode_with_len(bytes)?;
+        let mut vu64 = Vu64 {
+            length: len as u8,
+            bytes: [0u8; MAX_BYTES],
+        };
+        vu64.bytes[..len].copy_from_slice(&bytes[..len]);
+        out.push(vu64);
+        bytes = &bytes[len..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Encode `values` choosing raw or delta encoding per value, and append them to `out`.
+///
+/// The values are written in groups of up to 8. Each group starts with a tag byte,
+/// followed by the `vu64` of each value in the group. The bit `i` (LSB first)
+/// of the tag byte tells how the `i`-th value of the group is encoded:
+///
+/// - `0`: raw, the value itself.
+/// - `1`: delta, the zigzag encoded `value.wrapping_sub(previous) as i64`.
+///
+/// The previous value of the first value is `0`. The delta is chosen only
+/// when it is shorter than the raw value, so a sequence of close values
+/// shrinks while scattered values cost only 1 bit each.
+/// Decode with [`decode_adaptive()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_adaptive, encode_adaptive};
+/// let values = [1_000_000, 1_000_001, 1_000_003, 5];
+/// let mut buf = Vec::new();
+/// encode_adaptive(&values, &mut buf);
+/// assert_eq!(buf, vec![0b0110, 0xC0, 0x12, 0x7A, 0x02, 0x04, 0x05]);
+/// let mut out = Vec::new();
+/// assert_eq!(decode_adaptive(&buf, &mut out), Ok(4));
+/// assert_eq!(out, values);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_adaptive(values: &[u64], out: &mut Vec<u8>) {
+    let mut prev = 0u64;
+    for group in values.chunks(8) {
+        let tag_pos = out.len();
+        out.push(0);
+        let mut tag = 0u8;
+        for (i, &val) in group.iter().enumerate() {
+            let delta = signed::zigzag::encode(val.wrapping_sub(prev) as i64);
+            let encoded = if encoded_len(delta) < encoded_len(val) {
+                tag |= 1 << i;
+                encode(delta)
+            } else {
+                encode(val)
+            };
+            out.extend_from_slice(encoded.as_ref());
+            prev = val;
+        }
+        out[tag_pos] = tag;
+    }
+}
+
+/// Decode `bytes` encoded by [`encode_adaptive()`] into `out`.
+///
+/// Returns the number of values pushed.
+#[cfg(feature = "alloc")]
+pub fn decode_adaptive(mut bytes: &[u8], out: &mut Vec<u64>) -> Result<usize, Error> {
+    let mut prev = 0u64;
+    let mut count = 0;
+    while !bytes.is_empty() {
+        let tag = bytes[0];
+        bytes = &bytes[1..];
+        if bytes.is_empty() {
+            return Err(Error::Truncated);
+        }
+        let mut i = 0;
+        while i < 8 && !bytes.is_empty() {
+            let (encoded, len) = decode_with_len(bytes)?;
+            let val = if tag & (1 << i) != 0 {
+                prev.wrapping_add(signed::zigzag::decode(encoded) as u64)
+            } else {
+                encoded
+            };
+            out.push(val);
+            prev = val;
+            bytes = &bytes[len..];
+            i += 1;
+        }
+        count += i;
+    }
+    Ok(count)
+}
+
+/// Encode a sorted set of `values` with delta and run-length encoding, and append them to `out`.
+///
+/// The values are split into runs of consecutive values, each `1` greater than
+/// the previous one. Each run is written as a pair of `vu64`:
+///
+/// - `gap`: the first value of the run minus the last value of the previous run.
+///   For the first run, this is the first value itself.
+/// - `extra`: the number of values of the run after the first one.
+///
+/// For example, `[1, 2, 3, 4, 10, 11, 20]` is written as the pairs
+/// `(1, 3), (6, 1), (9, 0)`. Dense sets such as posting lists shrink to a few
+/// bytes per run, while an isolated value costs 1 byte for `extra`.
+/// The subtractions are wrapping and a run ends at `u64::MAX`, so any sequence
+/// can be encoded, but it is compact only when `values` are sorted in ascending order.
+/// Decode with [`decode_sorted_set()`]. Note that the decoder rejects more than
+/// [`MAX_SORTED_SET_LEN`] values, so a longer `values` is encoded but fails to decode
+/// with `Error::TooManyValues`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_sorted_set, encode_sorted_set};
+/// let values = [1, 2, 3, 4, 10, 11, 20];
+/// let mut buf = Vec::new();
+/// encode_sorted_set(&values, &mut buf);
+/// assert_eq!(buf, vec![1, 3, 6, 1, 9, 0]);
+/// let mut out = Vec::new();
+/// assert_eq!(decode_sorted_set(&buf, &mut out), Ok(7));
+/// assert_eq!(out, values);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_sorted_set(values: &[u64], out: &mut Vec<u8>) {
+    let mut prev = 0u64;
+    let mut i = 0;
+    while i < values.len() {
+        let first = values[i];
+        let mut extra = 0u64;
+        while i + 1 < values.len() && values[i].checked_add(1) == Some(values[i + 1]) {
+            extra += 1;
+            i += 1;
+        }
+        out.extend_from_slice(encode(first.wrapping_sub(prev)).as_ref());
+        out.extend_from_slice(encode(extra).as_ref());
+        prev = values[i];
+        i += 1;
+    }
+}
+
+/// The maximum number of values decoded by one [`decode_sorted_set()`].
+///
+/// A run of a few bytes can expand to a huge number of values, so the decoder
+/// rejects an input expanding beyond this limit.
+pub const MAX_SORTED_SET_LEN: usize = 1 << 24;
+
+/// Decode `bytes` encoded by [`encode_sorted_set()`] into `out`.
+///
+/// Returns the number of values pushed.
+/// If a `gap` is not followed by its `extra`, `Error::Truncated` is returned.
+/// If a run goes beyond `u64::MAX`, `Error::NotMonotonic` is returned, and if
+/// the values exceed [`MAX_SORTED_SET_LEN`], `Error::TooManyValues`.
+/// Both are checked before the run is pushed to `out`.
+#[cfg(feature = "alloc")]
+pub fn decode_sorted_set(mut bytes: &[u8], out: &mut Vec<u64>) -> Result<usize, Error> {
+    let mut prev = 0u64;
+    let mut count: usize = 0;
+    while !bytes.is_empty() {
+        let (gap, len) = decode_with_len(bytes)?;
+        bytes = &bytes[len..];
+        let (extra, len) = decode_with_len(bytes)?;
+        bytes = &bytes[len..];
+        let first = prev.wrapping_add(gap);
+        let last = first.checked_add(extra).ok_or(Error::NotMonotonic)?;
+        count = usize::try_from(extra)
+            .ok()
+            .and_then(|extra| count.checked_add(extra))
+            .and_then(|count| count.checked_add(1))
+            .filter(|&count| count <= MAX_SORTED_SET_LEN)
+            .ok_or(Error::TooManyValues)?;
+        out.extend(first..=last);
+        prev = last;
+    }
+    Ok(count)
+}
+
+/// A buffer of concatenated `vu64` values, validated once.
+///
+/// [`ParsedBuffer::new()`] checks that every value of the buffer is complete
+/// and canonical. After that, [`iter()`](ParsedBuffer::iter) and
+/// [`get()`](ParsedBuffer::get) decode without the checks, so scanning the same
+/// buffer many times pays the validation only once.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::ParsedBuffer;
+/// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+/// let parsed = ParsedBuffer::new(&buf).unwrap();
+/// assert_eq!(parsed.len(), 3);
+/// assert_eq!(parsed.iter().collect::<Vec<_>>(), vec![1, 0x0f0f, 127]);
+/// assert_eq!(parsed.get(1), Some(0x0f0f));
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct ParsedBuffer<'a> {
+    bytes: &'a [u8],
+    count: usize,
+}
+
+impl<'a> ParsedBuffer<'a> {
+    /// Validate all values of `bytes`, and return the handle.
+    pub fn new(bytes: &'a [u8]) -> Result<ParsedBuffer<'a>, Error> {
+        let mut rest = bytes;
+        let mut count = 0;
+        while !rest.is_empty() {
+            let (_, len) = decode_with_len(rest)?;
+            rest = &rest[len..];
+            count += 1;
+        }
+        Ok(ParsedBuffer { bytes, count })
+    }
+
+    /// The number of values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if the buffer has no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The underlying bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Iterate the values.
+    #[inline]
+    pub fn iter(&self) -> ParsedIter<'a> {
+        ParsedIter {
+            bytes: self.bytes,
+            remaining: self.count,
+        }
+    }
+
+    /// Get the value at `index`, or `None` if it is out of bounds.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<u64> {
+        self.iter().nth(index)
+    }
+
+    /// Iterate the values in both directions.
+    ///
+    /// This builds the offset index of the values in one scan,
+    /// like [`build_offset_index()`], so the returned iterator is
+    /// a `DoubleEndedIterator` and can also iterate from the back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vu64::ParsedBuffer;
+    /// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+    /// let parsed = ParsedBuffer::new(&buf).unwrap();
+    /// assert_eq!(parsed.indexed_iter().rev().collect::<Vec<_>>(), vec![127, 0x0f0f, 1]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn indexed_iter(&self) -> IndexedIter<'a> {
+        let mut index = Vec::with_capacity(self.count);
+        let mut pos = 0;
+        while pos < self.bytes.len() {
+            index.push(pos);
+            pos += decoded_len(self.bytes[pos]) as usize;
+        }
+        IndexedIter {
+            bytes: self.bytes,
+            front: 0,
+            back: index.len(),
+            index,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &ParsedBuffer<'a> {
+    type Item = u64;
+    type IntoIter = ParsedIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> ParsedIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the values of a [`ParsedBuffer`].
+#[derive(Clone, Debug)]
+pub struct ParsedIter<'a> {
+    bytes: &'a [u8],
+    remaining: usize,
+}
+
+impl<'a> Iterator for ParsedIter<'a> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let length = decoded_len(self.bytes[0]);
+        // SAFETY: the buffer is validated by `ParsedBuffer::new()`.
+        let val = unsafe { decode_value_with_length(length, self.bytes) };
+        self.bytes = &self.bytes[length as usize..];
+        self.remaining -= 1;
+        Some(val)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for ParsedIter<'a> {}
+
+/// A double-ended iterator over the values of a [`ParsedBuffer`],
+/// returned by [`ParsedBuffer::indexed_iter()`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct IndexedIter<'a> {
+    bytes: &'a [u8],
+    index: Vec<usize>,
+    front: usize,
+    back: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> IndexedIter<'a> {
+    #[inline]
+    fn value_at(&self, i: usize) -> u64 {
+        let bytes = &self.bytes[self.index[i]..];
+        let length = decoded_len(bytes[0]);
+        // SAFETY: the buffer is validated by `ParsedBuffer::new()`.
+        unsafe { decode_value_with_length(length, bytes) }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for IndexedIter<'a> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<u64> {
+        if self.front == self.back {
+            return None;
+        }
+        let val = self.value_at(self.front);
+        self.front += 1;
+        Some(val)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> DoubleEndedIterator for IndexedIter<'a> {
+    #[inline]
+    fn next_back(&mut self) -> Option<u64> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.value_at(self.back))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> ExactSizeIterator for IndexedIter<'a> {}
+
+/// Decode the `vu64` value at `*pos` of `bytes`, and advance `*pos` past it.
+///
+/// This is for parsers that track an index into an owned buffer rather than
+/// reslicing it. On error, `*pos` is not changed. If `*pos` is at or beyond
+/// the end of `bytes`, `Error::Truncated` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_at;
+/// let buf = [0x01, 0x8F, 0x3c];
+/// let mut pos = 0;
+/// assert_eq!(decode_at(&buf, &mut pos), Ok(1));
+/// assert_eq!(decode_at(&buf, &mut pos), Ok(0x0f0f));
+/// assert_eq!(pos, 3);
+/// ```
+#[inline]
+pub fn decode_at(bytes: &[u8], pos: &mut usize) -> Result<u64, Error> {
+    let rest = bytes.get(*pos..).unwrap_or(&[]);
+    let (val, len) = decode_with_len(rest)?;
+    *pos += len;
+    Ok(val)
+}
+
+/// Encode `value` followed by a checksum byte, and append them to `out`.
+///
+/// The checksum byte is the XOR of all the encoded bytes of `value`.
+/// This is a lightweight framing to detect a corruption, e.g. a flipped bit,
+/// and is not a cryptographic protection. Decode with [`decode_with_checksum()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_with_checksum, encode_with_checksum};
+/// let mut buf = Vec::new();
+/// encode_with_checksum(0x0f0f, &mut buf);
+/// assert_eq!(buf, vec![0x8F, 0x3c, 0x8F ^ 0x3c]);
+/// assert_eq!(decode_with_checksum(&buf), Ok((0x0f0f, 3)));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_with_checksum(value: u64, out: &mut Vec<u8>) {
+    let encoded = encode(value);
+    let bytes = encoded.as_ref();
+    out.extend_from_slice(bytes);
+    out.push(checksum(bytes));
+}
+
+/// Decode a `vu64` value followed by a checksum byte written by [`encode_with_checksum()`].
+///
+/// Returns the value and the consumed length including the checksum byte.
+/// If the checksum byte does not match, `Error::ChecksumMismatch` is returned.
+pub fn decode_with_checksum(bytes: &[u8]) -> Result<(u64, usize), Error> {
+    let (val, len) = decode_with_len(bytes)?;
+    match bytes.get(len) {
+        None => Err(Error::Truncated),
+        Some(&sum) if sum == checksum(&bytes[..len]) => Ok((val, len + 1)),
+        Some(_) => Err(Error::ChecksumMismatch),
+    }
+}
+
+#[inline]
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |acc, &b| acc ^ b)
+}
+
+/// Build the index of the start offset of every `vu64` value in `bytes`.
+///
+/// After this single scan, the `i`-th value can be decoded directly with
+/// `decode(&bytes[index[i]..])`, so random access and reverse iteration need
+/// no more forward scans.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{build_offset_index, decode};
+/// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+/// let index = build_offset_index(&buf).unwrap();
+/// assert_eq!(index, vec![0, 1, 3]);
+/// assert_eq!(decode(&buf[index[2]..]), Ok(127));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn build_offset_index(bytes: &[u8]) -> Result<Vec<usize>, Error> {
+    let mut index = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let (_, len) = decode_with_len(&bytes[pos..])?;
+        index.push(pos);
+        pos += len;
+    }
+    Ok(index)
+}
+
+/// Copy the `vu64` values in `bytes` to `out`, omitting the first occurrence of `target`.
+///
+/// Returns whether `target` was found. Since the encoding is canonical,
+/// the byte ranges are copied as they are without re-encoding.
+/// If an error occurs, the bytes before the invalid value have already been
+/// appended to `out`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::remove_value;
+/// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+/// let mut out = Vec::new();
+/// assert_eq!(remove_value(&buf, 0x0f0f, &mut out), Ok(true));
+/// assert_eq!(out, vec![0x01, 0x7F]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn remove_value(bytes: &[u8], target: u64, out: &mut Vec<u8>) -> Result<bool, Error> {
+    let mut found = false;
+    let mut start = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let value_pos = pos;
+        let val = decode_at(bytes, &mut pos)?;
+        if !found && val == target {
+            out.extend_from_slice(&bytes[start..value_pos]);
+            start = pos;
+            found = true;
+        }
+    }
+    out.extend_from_slice(&bytes[start..]);
+    Ok(found)
+}
+
+/// Decode the first `vu64` value of `bytes`, and check whether it is in `range`.
+///
+/// Nothing is consumed, so the caller can decide whether to consume the value,
+/// e.g. with [`decode_at()`]. This lets a scanner skip a block whose first value
+/// is out of range.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::peek_in_range;
+/// let buf = [0x8F, 0x3c, 0x01];
+/// assert_eq!(peek_in_range(&buf, 0x0f00..=0x0fff), Ok(true));
+/// assert_eq!(peek_in_range(&buf, 0..=127), Ok(false));
+/// ```
+#[inline]
+pub fn peek_in_range(bytes: &[u8], range: RangeInclusive<u64>) -> Result<bool, Error> {
+    let (val, _) = decode_with_len(bytes)?;
+    Ok(range.contains(&val))
+}
+
+/// Decode every `vu64` value of `bytes`, and return the sum of them.
+///
+/// The values are summed in one pass without materializing them.
+/// The sum is wrapping: if it overflows `u64`, it wraps around at `2^64`,
+/// the same as [`u64::wrapping_add()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::sum_values;
+/// assert_eq!(sum_values(&[0x01, 0x8F, 0x3c, 0x7F]), Ok(1 + 0x0f0f + 127));
+/// ```
+pub fn sum_values(mut bytes: &[u8]) -> Result<u64, Error> {
+    let mut sum = 0u64;
+    while !bytes.is_empty() {
+        let (val, len) = decode_with_len(bytes)?;
+        sum = sum.wrapping_add(val);
+        bytes = &bytes[len..];
+    }
+    Ok(sum)
+}
+
+/// Encode all `values` into a new buffer of concatenated `vu64` values.
+///
+/// This is the inverse of [`unpack()`]: `unpack(&pack(values)) == Ok(values.to_vec())`
+/// holds for any `values`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{pack, unpack};
+/// let buf = pack(&[1, 0x0f0f, 127]);
+/// assert_eq!(buf, vec![0x01, 0x8F, 0x3c, 0x7F]);
+/// assert_eq!(unpack(&buf), Ok(vec![1, 0x0f0f, 127]));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn pack(values: &[u64]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(values.len());
+    for &val in values.iter() {
+        buf.extend_from_slice(encode(val).as_ref());
+    }
+    buf
+}
+
+/// Decode all values of a buffer of concatenated `vu64` values.
+///
+/// This is the inverse of [`pack()`].
+#[cfg(feature = "alloc")]
+pub fn unpack(bytes: &[u8]) -> Result<Vec<u64>, Error> {
+    let mut values = Vec::new();
+    decode_slice_push(bytes, &mut values)?;
+    Ok(values)
+}
+
+/// Decode the `vu64` values of `bytes` into any collection `out` that implements `Extend<u64>`.
+///
+/// This decodes into a `Vec`, a `VecDeque` or a custom collection uniformly.
+/// Returns the number of values pushed.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::VecDeque;
+/// use vu64::decode_slice_push;
+/// let mut out = VecDeque::new();
+/// assert_eq!(decode_slice_push(&[0x01, 0x8F, 0x3c], &mut out), Ok(2));
+/// assert_eq!(out, VecDeque::from(vec![1, 0x0f0f]));
+/// ```
+pub fn decode_slice_push<C: Extend<u64>>(mut bytes: &[u8], out: &mut C) -> Result<usize, Error> {
+    let mut count = 0;
+    while !bytes.is_empty() {
+        let (val, len) = decode_with_len(bytes)?;
+        out.extend(core::iter::once(val));
+        bytes = &bytes[len..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decode the `vu64` values of `bytes`, apply `f` to each of them, and push the results into `out`.
+///
+/// This fuses the decoding and the transform in one pass, without an intermediate `Vec<u64>`.
+/// Returns the number of values pushed. On error, the values before the bad one
+/// remain pushed in `out`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_slice_map;
+/// let mut out = Vec::new();
+/// assert_eq!(decode_slice_map(&[0x01, 0x8F, 0x3c], |v| v * 2, &mut out), Ok(2));
+/// assert_eq!(out, vec![2, 0x1e1e]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_slice_map<T, F: FnMut(u64) -> T>(
+    mut bytes: &[u8],
+    mut f: F,
+    out: &mut Vec<T>,
+) -> Result<usize, Error> {
+    let mut count = 0;
+    while !bytes.is_empty() {
+        let (val, len) = decode_with_len(bytes)?;
+        out.push(f(val));
+        bytes = &bytes[len..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decode the `vu64` values of `bytes` into the caller-provided slice `out`.
+///
+/// Unlike decoding into a `Vec`, this does not allocate.
+/// Returns the number of values decoded. If `bytes` has more values than
+/// `out.len()`, `Error::BufferFull` is returned, and `out` is filled with
+/// the leading values.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_slice_into, Error};
+/// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+/// let mut out = [0u64; 4];
+/// assert_eq!(decode_slice_into(&buf, &mut out), Ok(3));
+/// assert_eq!(&out[..3], &[1, 0x0f0f, 127]);
+/// let mut out = [0u64; 2];
+/// assert_eq!(decode_slice_into(&buf, &mut out), Err(Error::BufferFull));
+/// ```
+pub fn decode_slice_into(mut bytes: &[u8], out: &mut [u64]) -> Result<usize, Error> {
+    let mut count = 0;
+    while !bytes.is_empty() {
+        let slot = out.get_mut(count).ok_or(Error::BufferFull)?;
+        let (val, len) = decode_with_len(bytes)?;
+        *slot = val;
+        bytes = &bytes[len..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decode the `vu64` value at the head of `bytes` as `f64`.
+///
+/// Returns the value and the consumed length.
+/// Values up to `2^53` are converted exactly. Above `2^53`, `f64` can not
+/// hold every integer, so the value is rounded to the nearest representable
+/// `f64`, the same as `value as f64`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_as_f64, encode};
+/// assert_eq!(decode_as_f64(&[0x8F, 0x3c]), Ok((3855.0, 2)));
+/// // 2^53 + 1 is rounded to 2^53
+/// let buf = encode((1 << 53) + 1);
+/// assert_eq!(decode_as_f64(buf.as_ref()), Ok((9007199254740992.0, 8)));
+/// ```
+#[inline]
+pub fn decode_as_f64(bytes: &[u8]) -> Result<(f64, usize), Error> {
+    let (val, len) = decode_with_len(bytes)?;
+    Ok((val as f64, len))
+}
+
+/// Decode the `vu64` value at `offset` of `buffer`.
+///
+/// Returns the value and the offset of the next value, `offset + consumed`,
+/// so the returned offset can be passed to the next call as it is.
+/// If `offset` is at or beyond the end of `buffer`, `Error::Truncated` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_at_offset;
+/// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+/// let (val, offset) = decode_at_offset(&buf, 1).unwrap();
+/// assert_eq!((val, offset), (0x0f0f, 3));
+/// assert_eq!(decode_at_offset(&buf, offset), Ok((127, 4)));
+/// ```
+#[inline]
+pub fn decode_at_offset(buffer: &[u8], offset: usize) -> Result<(u64, usize), Error> {
+    let mut pos = offset;
+    let val = decode_at(buffer, &mut pos)?;
+    Ok((val, pos))
+}
+
+/// An encoder of a sequence of nondecreasing timestamps.
+///
+/// The first timestamp is written as it is, and each following one as
+/// the delta from the previous one, so a regular time series takes about
+/// 1 byte per timestamp. A timestamp less than the previous one is rejected
+/// with `Error::NotMonotonic`, while an equal one is written as the delta `0`.
+/// Decode with [`decode_time_series()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_time_series, Error, TimeSeriesEncoder};
+/// let mut enc = TimeSeriesEncoder::new();
+/// enc.push(1_700_000_000).unwrap();
+/// enc.push(1_700_000_010).unwrap();
+/// enc.push(1_700_000_010).unwrap();
+/// assert_eq!(enc.push(1_699_999_999), Err(Error::NotMonotonic));
+/// let mut out = Vec::new();
+/// assert_eq!(decode_time_series(enc.as_bytes(), &mut out), Ok(3));
+/// assert_eq!(out, vec![1_700_000_000, 1_700_000_010, 1_700_000_010]);
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, Default)]
+pub struct TimeSeriesEncoder {
+    buf: Vec<u8>,
+    last: Option<u64>,
+}
+
+#[cfg(feature = "alloc")]
+impl TimeSeriesEncoder {
+    /// Create an empty encoder.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `timestamp`.
+    ///
+    /// If `timestamp` is less than the previous one, `Error::NotMonotonic`
+    /// is returned and nothing is appended.
+    pub fn push(&mut self, timestamp: u64) -> Result<(), Error> {
+        let encoded = match self.last {
+            None => encode(timestamp),
+            Some(last) if timestamp >= last => encode(timestamp - last),
+            Some(_) => return Err(Error::NotMonotonic),
+        };
+        self.buf.extend_from_slice(encoded.as_ref());
+        self.last = Some(timestamp);
+        Ok(())
+    }
+
+    /// The encoded bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Unwrap this encoder, returning the encoded bytes.
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Decode `bytes` encoded by [`TimeSeriesEncoder`] into `out`.
+///
+/// Returns the number of timestamps pushed.
+/// If a delta overflows `u64`, `Error::NotMonotonic` is returned.
+#[cfg(feature = "alloc")]
+pub fn decode_time_series(mut bytes: &[u8], out: &mut Vec<u64>) -> Result<usize, Error> {
+    let mut last: Option<u64> = None;
+    let mut count = 0;
+    while !bytes.is_empty() {
+        let (val, len) = decode_with_len(bytes)?;
+        let timestamp = match last {
+            None => val,
+            Some(last) => last.checked_add(val).ok_or(Error::NotMonotonic)?,
+        };
+        out.push(timestamp);
+        last = Some(timestamp);
+        bytes = &bytes[len..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Get the total length in bytes of the encoded `Vu64` of all `values`.
+///
+/// This is the exact size of the output of [`pack()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::encoded_len_slice;
+/// assert_eq!(encoded_len_slice(&[0, 127, 128, u64::MAX]), 1 + 1 + 2 + 9);
+/// ```
+pub fn encoded_len_slice(values: &[u64]) -> usize {
+    values.iter().map(|&v| encoded_len(v) as usize).sum()
+}
+
+/// Get the total length in bytes of `values` delta encoded by [`TimeSeriesEncoder`].
+///
+/// This is the first value and the deltas of the following values.
+/// If `values` is not nondecreasing, `None` is returned. An adaptive encoder
+/// can compare this with [`encoded_len_slice()`] to decide whether to delta encode.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{delta_encoded_len_slice, encoded_len_slice};
+/// let values = [1_000_000, 1_000_001, 1_000_003];
+/// assert_eq!(delta_encoded_len_slice(&values), Some(3 + 1 + 1));
+/// assert_eq!(encoded_len_slice(&values), 3 + 3 + 3);
+/// assert_eq!(delta_encoded_len_slice(&[2, 1]), None);
+/// ```
+pub fn delta_encoded_len_slice(values: &[u64]) -> Option<usize> {
+    let mut total = 0;
+    let mut last: Option<u64> = None;
+    for &val in values {
+        let encoded_val = match last {
+            None => val,
+            Some(last) if val >= last => val - last,
+            Some(_) => return None,
+        };
+        total += encoded_len(encoded_val) as usize;
+        last = Some(val);
+    }
+    Some(total)
+}
+
+/// Split `bytes` into sub-slices, each the exact bytes of one `vu64` value.
+///
+/// Each returned sub-slice can be decoded independently, e.g. on another thread.
+/// If a value is truncated or invalid, the error is returned.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::split_values;
+/// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+/// let chunks = split_values(&buf).unwrap();
+/// assert_eq!(chunks, vec![&[0x01][..], &[0x8F, 0x3c][..], &[0x7F][..]]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn split_values(bytes: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    let mut chunks = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        decode_at(bytes, &mut pos)?;
+        chunks.push(&bytes[start..pos]);
+    }
+    Ok(chunks)
+}
+
+/// A fuzz target that decodes arbitrary `data` with every decoder.
+///
+/// This runs [`decode()`], [`decode2()`], [`decode3()`] and [`signed::decode()`]
+/// on `data`, and panics if they disagree on the result. Since none of them
+/// should panic on any input, a panic means a bug. It can be wired into
+/// a `cargo fuzz` target as it is:
+///
+/// ```ignore
+/// libfuzzer_sys::fuzz_target!(|data: &[u8]| vu64::fuzz_decode(data));
+/// ```
+pub fn fuzz_decode(data: &[u8]) {
+    let r1 = decode(data);
+    let r_signed = signed::decode(data);
+    assert_eq!(r_signed, r1.map(signed::zigzag::decode));
+    if data.is_empty() {
+        assert_eq!(r1, Err(Error::Truncated));
+        return;
+    }
+    let r2 = decode2(data[0], &data[1..]);
+    assert_eq!(r2, r1);
+    // `decode3()` takes only the follow bytes of the value, and can not tell
+    // a truncation, so it agrees only on complete input.
+    let length = decoded_len(data[0]) as usize;
+    if data.len() >= length {
+        let mut follow_le = [0u8; 8];
+        follow_le[..length - 1].copy_from_slice(&data[1..length]);
+        let r3 = decode3(data[0], u64::from_le_bytes(follow_le));
+        assert_eq!(r3, r1);
+    }
+}
+
+/// Decode the `vu64` value at the head of `bytes`, accepting a redundant encoding.
+///
+/// Returns the value, whether it is canonically encoded, and the consumed length.
+/// Unlike [`decode()`], a redundant encoding is not an error but flagged with
+/// `false`, so a lenient parser can accept it and log a warning.
+/// Only a truncation is an error.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_with_canonical_flag;
+/// assert_eq!(decode_with_canonical_flag(&[0x8F, 0x3c]), Ok((0x0f0f, true, 2)));
+/// // 1 in 2 bytes
+/// assert_eq!(decode_with_canonical_flag(&[0x81, 0x00]), Ok((1, false, 2)));
+/// ```
+pub fn decode_with_canonical_flag(bytes: &[u8]) -> Result<(u64, bool, usize), Error> {
+    if bytes.is_empty() {
+        return Err(Error::Truncated);
+    }
+    let length = decoded_len(bytes[0]);
+    if bytes.len() < length as usize {
+        return Err(Error::Truncated);
+    }
+    // SAFETY: `bytes` has at least `length` bytes, checked above.
+    let result = unsafe { decode_value_with_length(length, bytes) };
+    let canonical = length == 1 || result >= (1 << (7 * (length - 1)));
+    Ok((result, canonical, length as usize))
+}
+
+/// Decode a `vu64` value at the head of `bytes` without any checks.
+///
+/// This skips both the truncation and the redundant encoding checks,
+/// so it is the fastest decoding for trusted data, e.g. a buffer already
+/// validated by [`ParsedBuffer::new()`].
+///
+/// # Safety
+///
+/// `bytes` must start with a complete `vu64`, that is, `bytes.len()` must be
+/// at least `decoded_len(bytes[0])`, and `bytes` must not be empty.
+/// A redundant encoding is not undefined behavior, but its value is returned as it is.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_unchecked;
+/// let buf = [0x8F, 0x3c];
+/// assert_eq!(unsafe { decode_unchecked(&buf) }, 0x0f0f);
+/// ```
+#[inline]
+pub unsafe fn decode_unchecked(bytes: &[u8]) -> u64 {
+    let length = decoded_len(*bytes.get_unchecked(0));
+    decode_value_with_length(length, bytes)
+}
+
+/// Estimate the total encoded bytes from a histogram of encoded lengths.
+///
+/// `histogram[i]` is the number of values whose encoded length is `i + 1` bytes,
+/// e.g. counted with [`encoded_len()`] over a sample.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::estimate_bytes;
+/// // 10 values of 1 byte, 5 values of 2 bytes and 1 value of 9 bytes
+/// let histogram = [10, 5, 0, 0, 0, 0, 0, 0, 1];
+/// assert_eq!(estimate_bytes(&histogram), 10 + 5 * 2 + 9);
+/// ```
+pub fn estimate_bytes(histogram: &[usize; MAX_BYTES]) -> usize {
+    histogram
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| count * (i + 1))
+        .sum()
+}
+
+/// Decode the `vu64` value at the head of `bytes` into `*out`.
+///
+/// Returns the consumed length. On error, `*out` is not changed.
+/// This fits the style that preallocates the outputs, e.g. a ring buffer.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_ref;
+/// let mut val = 0;
+/// assert_eq!(decode_ref(&[0x8F, 0x3c], &mut val), Ok(2));
+/// assert_eq!(val, 0x0f0f);
+/// ```
+#[inline]
+pub fn decode_ref(bytes: &[u8], out: &mut u64) -> Result<usize, Error> {
+    let (val, len) = decode_with_len(bytes)?;
+    *out = val;
+    Ok(len)
+}
+
+/// Iterate the encoded length of each `vu64` value in `bytes` without decoding the values.
+///
+/// Only the first byte of each value is read with [`decoded_len()`], so this is
+/// faster than a full decoding when only the layout is needed.
+/// If the last value is truncated, `Error::Truncated` is yielded and the iteration ends.
+/// Since the values are not decoded, a redundant encoding is not detected.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::len_iter;
+/// let buf = [0x01, 0x8F, 0x3c, 0x7F];
+/// let lens: Result<Vec<u8>, _> = len_iter(&buf).collect();
+/// assert_eq!(lens, Ok(vec![1, 2, 1]));
+/// ```
+pub fn len_iter(mut bytes: &[u8]) -> impl Iterator<Item = Result<u8, Error>> + '_ {
+    core::iter::from_fn(move || {
+        let first = *bytes.first()?;
+        let length = decoded_len(first);
+        if bytes.len() < length as usize {
+            bytes = &[];
+            return Some(Err(Error::Truncated));
+        }
+        bytes = &bytes[length as usize..];
+        Some(Ok(length))
+    })
+}
+
+/// Get the largest encoded length of the `vu64` values in `bytes`.
+///
+/// This is the minimal fixed width that can hold every value, e.g. to switch
+/// to a fixed layout. Returns 0 for an empty buffer. If the last value is truncated,
+/// `Error::Truncated` is returned. Like [`len_iter()`], the values are not decoded,
+/// so a redundant encoding is not detected.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::max_encoded_len;
+/// assert_eq!(max_encoded_len(&[0x01, 0x8F, 0x3c, 0x7F]), Ok(2));
+/// ```
+pub fn max_encoded_len(bytes: &[u8]) -> Result<u8, Error> {
+    let mut max = 0;
+    for r in len_iter(bytes) {
+        max = max.max(r?);
+    }
+    Ok(max)
+}
+
+/// Decode the `vu64` value at the head of `bytes`, or return `default` on any error.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_or;
+/// assert_eq!(decode_or(&[0x8F, 0x3c], 7), 0x0f0f);
+/// assert_eq!(decode_or(&[0x8F], 7), 7);
+/// ```
+#[inline]
+pub fn decode_or(bytes: &[u8], default: u64) -> u64 {
+    decode(bytes).unwrap_or(default)
+}
+
+/// Decode the `vu64` value at the head of `bytes`, or compute a value from the error by `f`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_or_else, Error};
+/// assert_eq!(decode_or_else(&[0x8F, 0x3c], |_| 7), 0x0f0f);
+/// let val = decode_or_else(&[], |err| if err == Error::Truncated { 7 } else { 8 });
+/// assert_eq!(val, 7);
+/// ```
+#[inline]
+pub fn decode_or_else<F>(bytes: &[u8], f: F) -> u64
+where
+    F: FnOnce(Error) -> u64,
+{
+    decode(bytes).unwrap_or_else(f)
+}
+
+/// Validate two buffers of `vu64` values, and append both to `out`.
+///
+/// Both `a` and `b` must be sequences of complete and canonical values.
+/// Simply concatenating a buffer with a truncated tail would corrupt the value
+/// at the boundary, so both are validated before anything is appended.
+/// Returns the total number of values of `a` and `b`.
+/// On error, `out` is not changed.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{concat_validated, Error};
+/// let mut out = Vec::new();
+/// assert_eq!(concat_validated(&[0x01, 0x8F, 0x3c], &[0x7F], &mut out), Ok(3));
+/// assert_eq!(out, vec![0x01, 0x8F, 0x3c, 0x7F]);
+/// assert_eq!(concat_validated(&[0x01, 0x8F], &[0x3c], &mut out), Err(Error::Truncated));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn concat_validated(a: &[u8], b: &[u8], out: &mut Vec<u8>) -> Result<usize, Error> {
+    let count_a = ParsedBuffer::new(a)?.len();
+    let count_b = ParsedBuffer::new(b)?.len();
+    out.reserve(a.len() + b.len());
+    out.extend_from_slice(a);
+    out.extend_from_slice(b);
+    Ok(count_a + count_b)
+}
+
+/// Decode a sparse column of `count` entries from a presence bitmap and the present values.
+///
+/// The bit `i % 8` (LSB first) of `bitmap[i / 8]` tells whether the `i`-th entry
+/// is present. `values` has the `vu64` values of only the present entries, in order.
+/// An absent entry is decoded as `None`.
+///
+/// - `Error::Truncated`: `bitmap` has less than `count` bits, or `values` runs out.
+/// - `Error::TrailingBytes`: `values` has more bytes after the last present value.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_sparse;
+/// // the entries 0 and 2 are present
+/// let r = decode_sparse(&[0b0101], &[0x01, 0x8F, 0x3c], 3);
+/// assert_eq!(r, Ok(vec![Some(1), None, Some(0x0f0f)]));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_sparse(
+    bitmap: &[u8],
+    values: &[u8],
+    count: usize,
+) -> Result<Vec<Option<u64>>, Error> {
+    if bitmap.len() * 8 < count {
+        return Err(Error::Truncated);
+    }
+    let mut out = Vec::with_capacity(count);
+    let mut pos = 0;
+    for i in 0..count {
+        if bitmap[i / 8] & (1 << (i % 8)) != 0 {
+            out.push(Some(decode_at(values, &mut pos)?));
+        } else {
+            out.push(None);
+        }
+    }
+    if pos != values.len() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(out)
+}
+
+/// Compute a stable fingerprint of a buffer of `vu64` values.
+///
+/// The fingerprint is the 64-bit FNV-1a hash of the bytes. Since the encoding
+/// is canonical, the same sequence of values is always encoded to the same
+/// bytes, and so gets the same fingerprint. This is a fast non-cryptographic
+/// hash for a content-addressed key, and the algorithm is stable across versions.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::fingerprint;
+/// // the encoded bytes of [1, 2, 0x0f0f] and [0x0f0f, 2, 1]
+/// let a = [0x01, 0x02, 0x8F, 0x3c];
+/// let b = [0x8F, 0x3c, 0x02, 0x01];
+/// assert_eq!(fingerprint(&a), fingerprint(&a.clone()));
+/// assert_ne!(fingerprint(&a), fingerprint(&b));
+/// ```
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Decode the `vu64` values of `bytes` into `out`, validating that they are strictly increasing.
+///
+/// Returns the number of values pushed. If a value is not greater than
+/// the previous one, `Error::NotMonotonic` is returned. This catches a corruption
+/// of sorted data early, e.g. a sorted index.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_slice_monotonic, Error};
+/// let mut out = Vec::new();
+/// assert_eq!(decode_slice_monotonic(&[0x01, 0x02, 0x8F, 0x3c], &mut out), Ok(3));
+/// assert_eq!(decode_slice_monotonic(&[0x01, 0x01], &mut out), Err(Error::NotMonotonic));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_slice_monotonic(mut bytes: &[u8], out: &mut Vec<u64>) -> Result<usize, Error> {
+    let mut prev: Option<u64> = None;
+    let mut count = 0;
+    while !bytes.is_empty() {
+        let (val, len) = decode_with_len(bytes)?;
+        if matches!(prev, Some(prev) if val <= prev) {
+            return Err(Error::NotMonotonic);
+        }
+        out.push(val);
+        prev = Some(val);
+        bytes = &bytes[len..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decode the `vu64` value at the head of `bytes`, and return it with the prefix byte.
+///
+/// Returns the value, the first (prefix) byte and the consumed length.
+/// This is for the protocols that overload the meaning of the prefix byte,
+/// and keeps the two in sync without a separate `bytes[0]` read.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::decode_with_prefix;
+/// assert_eq!(decode_with_prefix(&[0x8F, 0x3c]), Ok((0x0f0f, 0x8F, 2)));
+/// ```
+#[inline]
+pub fn decode_with_prefix(bytes: &[u8]) -> Result<(u64, u8, usize), Error> {
+    let (val, len) = decode_with_len(bytes)?;
+    Ok((val, bytes[0], len))
+}
+
+/// Rewrite every `vu64` value of `bytes` with its canonical encoding, in place.
+///
+/// Each value is decoded leniently, accepting a redundant encoding, and written
+/// back with the minimal length, so the buffer may shrink. This repairs
+/// a buffer written by a buggy producer. Returns the new length.
+/// If a value is truncated, `Error::Truncated` is returned, and `bytes` is not changed.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::recanonicalize_in_place;
+/// // 1 in 2 bytes, and 0x0f0f in 2 bytes
+/// let mut buf = vec![0x81, 0x00, 0x8F, 0x3c];
+/// assert_eq!(recanonicalize_in_place(&mut buf), Ok(3));
+/// assert_eq!(buf, vec![0x01, 0x8F, 0x3c]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn recanonicalize_in_place(bytes: &mut Vec<u8>) -> Result<usize, Error> {
+    for r in len_iter(bytes) {
+        r?;
+    }
+    let mut read = 0;
+    let mut write = 0;
+    while read < bytes.len() {
+        let (val, _, len) = decode_with_canonical_flag(&bytes[read..])?;
+        let encoded = encode(val);
+        let encoded = encoded.as_ref();
+        // the canonical encoding is never longer, so `write + encoded.len() <= read + len`.
+        bytes[write..write + encoded.len()].copy_from_slice(encoded);
+        read += len;
+        write += encoded.len();
+    }
+    bytes.truncate(write);
+    Ok(write)
+}
+
+/// Encode a ratio `num / den` as two consecutive `vu64` values, and append them to `out`.
+///
+/// The denominator is written as it is, even if it is zero;
+/// the meaning of a zero denominator is the caller's concern.
+/// Decode with [`decode_ratio()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_ratio, encode_ratio};
+/// let mut buf = Vec::new();
+/// encode_ratio(3, 0x0f0f, &mut buf);
+/// assert_eq!(buf, vec![0x03, 0x8F, 0x3c]);
+/// assert_eq!(decode_ratio(&buf), Ok((3, 0x0f0f, 3)));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_ratio(num: u64, den: u64, out: &mut Vec<u8>) {
+    out.extend_from_slice(encode(num).as_ref());
+    out.extend_from_slice(encode(den).as_ref());
+}
+
+/// Decode a ratio written by [`encode_ratio()`].
+///
+/// Returns the numerator, the denominator and the consumed length.
+/// If the denominator is missing, `Error::Truncated` is returned.
+pub fn decode_ratio(bytes: &[u8]) -> Result<(u64, u64, usize), Error> {
+    let (num, num_len) = decode_with_len(bytes)?;
+    let (den, den_len) = decode_with_len(&bytes[num_len..])?;
+    Ok((num, den, num_len + den_len))
+}
+
+/// Decode all `vu64` values of `bytes`, collecting the errors instead of stopping.
+///
+/// Returns the successfully decoded values, and the `(offset, error)` of each
+/// problematic value. A redundant encoding is reported with its offset and skipped,
+/// so the following values are still decoded. Only a truncation can not be
+/// skipped, so it is reported as the last error.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_slice_collect_errors, Error};
+/// // 1, 1 in 2 bytes, 0x0f0f, and truncated
+/// let buf = [0x01, 0x81, 0x00, 0x8F, 0x3c, 0xC0];
+/// let (values, errors) = decode_slice_collect_errors(&buf);
+/// assert_eq!(values, vec![1, 0x0f0f]);
+/// assert_eq!(errors, vec![(1, Error::RedundantEncode), (5, Error::Truncated)]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_slice_collect_errors(bytes: &[u8]) -> (Vec<u64>, Vec<(usize, Error)>) {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match decode_with_canonical_flag(&bytes[pos..]) {
+            Ok((val, true, len)) => {
+                values.push(val);
+                pos += len;
+            }
+            Ok((_, false, len)) => {
+                errors.push((pos, Error::RedundantEncode));
+                pos += len;
+            }
+            Err(err) => {
+                errors.push((pos, err));
+                break;
+            }
+        }
+    }
+    (values, errors)
+}
+
+/// The progress of decoding a `vu64` returned by [`decode_progress()`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DecodeProgress {
+    /// The value is complete, with the decoded value and the consumed length.
+    Complete(u64, usize),
+    /// The value is truncated, and needs this number of more bytes.
+    NeedMore(usize),
+    /// The value is invalid.
+    Invalid(Error),
+}
+
+/// Decode the `vu64` value at the head of `bytes`, reporting the progress.
+///
+/// Unlike a bare `Error::Truncated`, `DecodeProgress::NeedMore(n)` tells exactly
+/// how many more bytes are needed to complete the value, so a caller driving
+/// reads from a socket can request them. For an empty `bytes`, it needs 1 byte.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_progress, DecodeProgress, Error};
+/// assert_eq!(decode_progress(&[0x8F, 0x3c]), DecodeProgress::Complete(0x0f0f, 2));
+/// assert_eq!(decode_progress(&[0xE0]), DecodeProgress::NeedMore(3));
+/// assert_eq!(
+///     decode_progress(&[0x81, 0x00]),
+///     DecodeProgress::Invalid(Error::RedundantEncode)
+/// );
+/// ```
+pub fn decode_progress(bytes: &[u8]) -> DecodeProgress {
+    let first = match bytes.first() {
+        Some(&b) => b,
+        None => return DecodeProgress::NeedMore(1),
+    };
+    let length = decoded_len(first) as usize;
+    if bytes.len() < length {
+        return DecodeProgress::NeedMore(length - bytes.len());
+    }
+    match decode_with_len(bytes) {
+        Ok((val, len)) => DecodeProgress::Complete(val, len),
+        Err(err) => DecodeProgress::Invalid(err),
+    }
+}
+
+/// Encode all `values` followed by a footer of the count, and append them to `out`.
+///
+/// The footer is the count of the values as a fixed 8-byte little-endian integer,
+/// so a reader that knows the size of the buffer can find it at the end.
+/// Decode with [`decode_with_footer()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_with_footer, encode_with_footer};
+/// let mut buf = Vec::new();
+/// encode_with_footer(&[1, 0x0f0f], &mut buf);
+/// assert_eq!(buf, vec![0x01, 0x8F, 0x3c, 2, 0, 0, 0, 0, 0, 0, 0]);
+/// assert_eq!(decode_with_footer(&buf), Ok(vec![1, 0x0f0f]));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_with_footer(values: &[u64], out: &mut Vec<u8>) {
+    for &val in values.iter() {
+        out.extend_from_slice(encode(val).as_ref());
+    }
+    out.extend_from_slice(&(values.len() as u64).to_le_bytes());
+}
+
+/// Decode the values written by [`encode_with_footer()`].
+///
+/// The count of the footer is validated against the decoded values:
+/// if the values end before the count, `Error::Truncated` is returned,
+/// and if bytes remain after the count, `Error::TrailingBytes` is returned.
+#[cfg(feature = "alloc")]
+pub fn decode_with_footer(bytes: &[u8]) -> Result<Vec<u64>, Error> {
+    if bytes.len() < 8 {
+        return Err(Error::Truncated);
+    }
+    let (mut body, footer) = bytes.split_at(bytes.len() - 8);
+    let count = u64::from_le_bytes(footer.try_into().unwrap());
+    // a corrupted count must not cause a huge allocation
+    let mut values = Vec::with_capacity(count.min(body.len() as u64) as usize);
+    while (values.len() as u64) < count {
+        let (val, len) = decode_with_len(body)?;
+        values.push(val);
+        body = &body[len..];
+    }
+    if !body.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(values)
+}
+
+/// An iterator decoding a buffer of concatenated `vu64` values,
+/// returned by [`decode_iter()`].
+#[derive(Clone, Debug)]
+pub struct DecodeIter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for DecodeIter<'a> {
+    type Item = Result<u64, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        match decode_with_len(self.bytes) {
+            Ok((val, len)) => {
+                self.bytes = &self.bytes[len..];
+                Some(Ok(val))
+            }
+            Err(err) => {
+                self.bytes = &[];
+                Some(Err(err))
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::from(!self.bytes.is_empty()), Some(self.bytes.len()))
+    }
+}
+
+/// Iterate the values of a buffer of concatenated `vu64` values.
+///
+/// Each value is decoded and yielded in order. If a value is truncated or
+/// redundant, the error is yielded once and then the iteration ends.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_iter, Error};
+/// let v: Vec<_> = decode_iter(&[0x01, 0x8F, 0x3c, 0xC0]).collect();
+/// assert_eq!(v, vec![Ok(1), Ok(0x0f0f), Err(Error::Truncated)]);
+/// ```
+#[inline]
+pub fn decode_iter(bytes: &[u8]) -> DecodeIter<'_> {
+    DecodeIter { bytes }
+}
+
+/// Encode all values of an iterator into a new buffer of concatenated `vu64` values.
+///
+/// The capacity is reserved up front from the lower bound of the size hint,
+/// as every value takes at least 1 byte. Decode with [`decode_iter()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::encode_all;
+/// let buf = encode_all((0..3).map(|v| v * 0x0f0f));
+/// assert_eq!(buf, vec![0x00, 0x8F, 0x3c, 0x9E, 0x78]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_all<I: IntoIterator<Item = u64>>(values: I) -> Vec<u8> {
+    let iter = values.into_iter();
+    let mut buf = Vec::with_capacity(iter.size_hint().0);
+    for val in iter {
+        buf.extend_from_slice(encode(val).as_ref());
+    }
+    buf
+}
+
+/// Interleave the `vu64` values of `a` and `b`, and append them to `out`.
+///
+/// One value is taken from each buffer in turn, so the output is
+/// `a[0], b[0], a[1], b[1], ...`. This converts two columns to rows.
+/// If the two buffers have different value counts, `Error::CountMismatch`
+/// is returned. On error, `out` is not changed.
+/// Reverse it with [`deinterleave()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{interleave, pack};
+/// let mut out = Vec::new();
+/// interleave(&pack(&[1, 2]), &pack(&[0x0f0f, 4]), &mut out).unwrap();
+/// assert_eq!(out, pack(&[1, 0x0f0f, 2, 4]));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn interleave(mut a: &[u8], mut b: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let count = ParsedBuffer::new(a)?.len();
+    if ParsedBuffer::new(b)?.len() != count {
+        return Err(Error::CountMismatch);
+    }
+    out.reserve(a.len() + b.len());
+    for _ in 0..count {
+        let len_a = decoded_len(a[0]) as usize;
+        let len_b = decoded_len(b[0]) as usize;
+        out.extend_from_slice(&a[..len_a]);
+        out.extend_from_slice(&b[..len_b]);
+        a = &a[len_a..];
+        b = &b[len_b..];
+    }
+    Ok(())
+}
+
+/// Deinterleave the `vu64` values of `bytes` into `a` and `b` alternately.
+///
+/// This is the inverse of [`interleave()`]. If the value count is odd,
+/// `Error::CountMismatch` is returned. On error, `a` and `b` are not changed.
+#[cfg(feature = "alloc")]
+pub fn deinterleave(mut bytes: &[u8], a: &mut Vec<u8>, b: &mut Vec<u8>) -> Result<(), Error> {
+    let count = ParsedBuffer::new(bytes)?.len();
+    if count % 2 != 0 {
+        return Err(Error::CountMismatch);
+    }
+    for i in 0..count {
+        let len = decoded_len(bytes[0]) as usize;
+        let col = if i % 2 == 0 { &mut *a } else { &mut *b };
+        col.extend_from_slice(&bytes[..len]);
+        bytes = &bytes[len..];
+    }
+    Ok(())
+}
+
+/// Encode a version byte followed by the `vu64` of `value`, and append them to `out`.
+///
+/// This is a preamble for a forward-compatible storage.
+/// Decode with [`decode_versioned()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{decode_versioned, encode_versioned, Error};
+/// let mut buf = Vec::new();
+/// encode_versioned(2, 0x0f0f, &mut buf);
+/// assert_eq!(buf, vec![0x02, 0x8F, 0x3c]);
+/// assert_eq!(decode_versioned(&buf, 2), Ok((0x0f0f, 3)));
+/// assert_eq!(decode_versioned(&buf, 1), Err(Error::Unexpected));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn encode_versioned(version: u8, value: u64, out: &mut Vec<u8>) {
+    out.push(version);
+    out.extend_from_slice(encode(value).as_ref());
+}
+
+/// Decode the value written by [`encode_versioned()`], checking the version byte.
+///
+/// Returns the value and the consumed length, including the version byte.
+/// If the version byte is not `expected_version`, `Error::Unexpected` is returned.
+pub fn decode_versioned(bytes: &[u8], expected_version: u8) -> Result<(u64, usize), Error> {
+    let (&version, rest) = bytes.split_first().ok_or(Error::Truncated)?;
+    if version != expected_version {
+        return Err(Error::Unexpected);
+    }
+    let (val, len) = decode_with_len(rest)?;
+    Ok((val, len + 1))
+}
+
+/// Encode a big-endian fixed-width unsigned integer as `vu64`.
+///
+/// The length of `bytes` must be 1, 2, 4 or 8. For any other length,
+/// including an empty `bytes`, `Error::InvalidWidth` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::{encode, from_be_fixed};
+/// assert_eq!(from_be_fixed(&[0x0f, 0x0f]), Ok(encode(0x0f0f)));
+/// assert_eq!(from_be_fixed(&[0, 0, 0, 0x7F]), Ok(encode(127)));
+/// ```
+pub fn from_be_fixed(bytes: &[u8]) -> Result<Vu64, Error> {
+    match bytes.len() {
+        1 | 2 | 4 | 8 => {
+            let mut buf = [0u8; 8];
+            buf[8 - bytes.len()..].copy_from_slice(bytes);
+            Ok(encode(u64::from_be_bytes(buf)))
+        }
+        _ => Err(Error::InvalidWidth),
+    }
+}
+
+/// Dump every `vu64` value of `bytes` as text, one line per value.
+///
+/// Each line is `index: value (len bytes) [0xXX, ...]`. If the tail of `bytes`
+/// is truncated or invalid, the last line is `index: error: message [0xXX, ...]`
+/// with the rest bytes, and the dump stops there. This is for the diagnostics
+/// of a serialization mismatch, e.g. in a test failure message.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::dump;
+/// assert_eq!(
+///     dump(&[0x01, 0x8F, 0x3c, 0x8F]),
+///     "0: 1 (1 bytes) [0x01]\n1: 3855 (2 bytes) [0x8f, 0x3c]\n2: error: truncated vu64 value [0x8f]\n"
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub fn dump(mut bytes: &[u8]) -> String {
+    use core::fmt::Write;
+    fn write_bytes(s: &mut String, bytes: &[u8]) {
+        s.push('[');
+        for (i, b) in bytes.iter().enumerate() {
+            if i > 0 {
+                s.push_str(", ");
+            }
+            let _ = write!(s, "0x{b:02x}");
+        }
+        s.push_str("]\n");
+    }
+    let mut s = String::new();
+    let mut index = 0;
+    while !bytes.is_empty() {
+        match decode_with_len(bytes) {
+            Ok((val, len)) => {
+                let _ = write!(s, "{index}: {val} ({len} bytes) ");
+                write_bytes(&mut s, &bytes[..len]);
+                bytes = &bytes[len..];
+            }
+            Err(err) => {
+                let _ = write!(s, "{index}: error: {err} ");
+                write_bytes(&mut s, bytes);
+                break;
+            }
+        }
+        index += 1;
+    }
+    s
+}
+
+/// Error type
+///
+/// New variants may be added in a minor version, so a `match` on it
+/// needs a wildcard arm.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Value contains unnecessary leading ones
+    LeadingOnes,
+
+    /// Value is truncated / malformed
+    Truncated,
+
+    /// Value is the redundant encoding
+    RedundantEncode,
+
+    /// Value is followed by unexpected bytes
+    TrailingBytes,
+
+    /// Value does not match its checksum
+    ChecksumMismatch,
+
+    /// Output buffer has no room for more values
+    BufferFull,
+
+    /// Text is not a valid base64
+    InvalidBase64,
+
+    /// Value is out of order in a monotonic sequence
+    NotMonotonic,
+
+    /// Value is not a discriminant of the enum
+    UnknownDiscriminant,
+
+    /// Value does not match the expected one, e.g. a version byte
+    Unexpected,
+
+    /// Decoded values exceed the limit
+    TooManyValues,
+
+    /// Number of values does not match the expected one
+    CountMismatch,
+
+    /// Fixed-width integer has an unsupported width
+    InvalidWidth,
+}
+
+impl Error {
+    /// Returns the stable machine-readable code of the error.
+    ///
+    /// Unlike the `Display` message, the code never changes between versions,
+    /// so logs can be grouped by it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// assert_eq!(vu64::Error::Truncated.code(), "truncated");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::LeadingOnes => "leading_ones",
+            Error::Truncated => "truncated",
+            Error::RedundantEncode => "redundant",
+            Error::TrailingBytes => "trailing_bytes",
+            Error::ChecksumMismatch => "checksum_mismatch",
+            Error::BufferFull => "buffer_full",
+            Error::InvalidBase64 => "invalid_base64",
+            Error::NotMonotonic => "not_monotonic",
+            Error::UnknownDiscriminant => "unknown_discriminant",
+            Error::Unexpected => "unexpected",
+            Error::TooManyValues => "too_many_values",
+            Error::CountMismatch => "count_mismatch",
+            Error::InvalidWidth => "invalid_width",
+        }
+    }
+    /// Returns the human-readable message of the error.
+    ///
+    /// This is the same string as the `Display` output, and is usable without a formatter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// assert_eq!(vu64::Error::Truncated.as_str(), "truncated vu64 value");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Error::LeadingOnes => "leading ones in vu64 value",
+            Error::Truncated => "truncated vu64 value",
+            Error::RedundantEncode => "redundant encoded vu64 value",
+            Error::TrailingBytes => "trailing bytes after vu64 value",
+            Error::ChecksumMismatch => "checksum mismatch of vu64 value",
+            Error::BufferFull => "output buffer is full",
+            Error::InvalidBase64 => "invalid base64 text of vu64 value",
+            Error::NotMonotonic => "out of order vu64 value in monotonic sequence",
+            Error::UnknownDiscriminant => "unknown enum discriminant in vu64 value",
+            Error::Unexpected => "unexpected vu64 value",
+            Error::TooManyValues => "too many vu64 values",
+            Error::CountMismatch => "mismatched count of vu64 values",
+            Error::InvalidWidth => "unsupported width of fixed integer",
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
+/// Maps `Truncated` to `UnexpectedEof`, `BufferFull` to `WriteZero`,
+/// and the others to `InvalidData`. The `Error` is kept as the inner error,
+/// so the `Display` message is preserved.
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> std::io::Error {
+        let kind = match err {
+            Error::Truncated => std::io::ErrorKind::UnexpectedEof,
+            Error::BufferFull => std::io::ErrorKind::WriteZero,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
+
+// a linear congruential generator, for reproducible random values in the tests
+#[cfg(test)]
+pub(crate) struct Lcg(pub(crate) u64);
+
+#[cfg(test)]
+impl Lcg {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0
+    }
+    // spread the values over every encoded length
+    #[cfg(feature = "alloc")]
+    pub(crate) fn next_spread(&mut self) -> u64 {
+        let r = self.next();
+        r >> (r >> 58) as u32
+    }
+}
+
+#[cfg(test)]
+mod test_u64_0 {
+    use super::{decode, decode2, decode3, encode};
+    //
+    #[test]
+    fn encode_bit_pattern_examples() {
+        assert_eq!(encode(0x0f0f).as_ref(), &[0x8F, 0x3c]);
+        assert_eq!(encode(0x0f0f_f0f0).as_ref(), &[0xE0, 0x0f, 0xff, 0xf0]);
+        assert_eq!(
+            encode(0x0f0f_f0f0_0f0f).as_ref(),
+            &[0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07]
+        );
+        assert_eq!(
+            encode(0x0f0f_f0f0_0f0f_f0f0).as_ref(),
+            &[0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f]
+        );
+    }
+    #[test]
+    fn decode_bit_pattern_examples() {
+        let slice = [0x8F, 0x3c].as_ref();
+        assert_eq!(decode(slice).unwrap(), 0x0f0f);
+
+        let slice = [0xE0, 0x0f, 0xff, 0xf0].as_ref();
+        assert_eq!(decode(slice).unwrap(), 0x0f0f_f0f0);
+
+        let slice = [0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07].as_ref();
+        assert_eq!(decode(slice).unwrap(), 0x0f0f_f0f0_0f0f);
+
+        let slice = [0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f].as_ref();
+        assert_eq!(decode(slice).unwrap(), 0x0f0f_f0f0_0f0f_f0f0);
+    }
+    #[test]
+    fn decode2_bit_pattern_examples() {
+        let slice = [0x8F, 0x3c].as_ref();
+        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), 0x0f0f);
+
+        let slice = [0xE0, 0x0f, 0xff, 0xf0].as_ref();
+        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), 0x0f0f_f0f0);
+
+        let slice = [0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07].as_ref();
+        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), 0x0f0f_f0f0_0f0f);
+
+        let slice = [0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f].as_ref();
+        assert_eq!(
+            decode2(slice[0], &slice[1..]).unwrap(),
+            0x0f0f_f0f0_0f0f_f0f0
+        );
+    }
+    #[test]
+    fn decode3_bit_pattern_examples() {
+        let slice = [0x8F, 0x3c].as_ref();
+        let mut buf = [0u8; 8];
+        buf[..1].copy_from_slice(&slice[1..]);
+        assert_eq!(decode3(slice[0], u64::from_le_bytes(buf)).unwrap(), 0x0f0f);
+
+        let slice = [0xE0, 0x0f, 0xff, 0xf0].as_ref();
+        let mut buf = [0u8; 8];
+        buf[..3].copy_from_slice(&slice[1..]);
+        assert_eq!(
+            decode3(slice[0], u64::from_le_bytes(buf)).unwrap(),
+            0x0f0f_f0f0
+        );
+
+        let slice = [0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07].as_ref();
+        let mut buf = [0u8; 8];
+        buf[..6].copy_from_slice(&slice[1..]);
+        assert_eq!(
+            decode3(slice[0], u64::from_le_bytes(buf)).unwrap(),
+            0x0f0f_f0f0_0f0f
+        );
+
+        let slice = [0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f].as_ref();
+        let mut buf = [0u8; 8];
+        buf[..8].copy_from_slice(&slice[1..]);
+        assert_eq!(
+            decode3(slice[0], u64::from_le_bytes(buf)).unwrap(),
+            0x0f0f_f0f0_0f0f_f0f0
+        );
+    }
+    #[test]
+    fn encode_decode() {
+        let val = 1234;
+        assert_eq!(decode(encode(val).as_ref()).unwrap(), val);
+        let val = 123456789;
+        assert_eq!(decode(encode(val).as_ref()).unwrap(), val);
+    }
+    #[test]
+    fn encode_decode2() {
+        let val = 1234;
+        let enc = encode(val);
+        let slice = enc.as_ref();
+        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), val);
+        let val = 123456789;
+        let enc = encode(val);
+        let slice = enc.as_ref();
+        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), val);
+    }
+    #[test]
+    fn encode_decode3() {
+        let val = 1234;
+        let enc = encode(val);
+        let slice = enc.as_ref();
+        let mut buf = [0u8; 8];
+        buf[..1].copy_from_slice(&slice[1..]);
+        assert_eq!(decode3(slice[0], u64::from_le_bytes(buf)).unwrap(), val);
+        let val = 123456789;
+        let enc = encode(val);
+        let slice = enc.as_ref();
+        let mut buf = [0u8; 8];
+        buf[..3].copy_from_slice(&slice[1..]);
+        assert_eq!(decode3(slice[0], u64::from_le_bytes(buf)).unwrap(), val);
+    }
+}
+
+#[cfg(test)]
+mod test_u64_1 {
+    use super::{decode, decode2, decode3, encode, encoded_len};
+    use super::{MAX_LEN1, MAX_LEN2, MAX_LEN3, MAX_LEN4, MAX_LEN5, MAX_LEN6, MAX_LEN7, MAX_LEN8};
+    #[test]
+    fn enc_dec_max_u64() {
+        let val = u64::MAX;
+        assert_eq!(encoded_len(val), 9);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(
+            encoded_slice,
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..8].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_u32() {
+        let val = u32::MAX.into();
+        assert_eq!(encoded_len(val), 5);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xf7, 0xff, 0xff, 0xff, 0x1f]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..4].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_u16() {
+        let val = u16::MAX.into();
+        assert_eq!(encoded_len(val), 3);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xdf, 0xff, 0x07]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..2].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_u8() {
+        let val = u8::MAX.into();
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xbf, 0x03]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_zero() {
+        let val = 0u64;
+        assert_eq!(encoded_len(val), 1);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0x00]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..0].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len1() {
+        let val = MAX_LEN1;
+        assert_eq!(encoded_len(val), 1);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0x7F]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..0].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0x80, 0x02]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len2() {
+        let val = MAX_LEN2;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xBF, 0xFF]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 3);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xC0, 0x00, 0x02]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..2].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len3() {
+        let val = MAX_LEN3;
+        assert_eq!(encoded_len(val), 3);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xDF, 0xFF, 0xFF]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..2].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 4);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xE0, 0x00, 0x00, 0x02]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..3].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len4() {
+        let val = MAX_LEN4;
+        assert_eq!(encoded_len(val), 4);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xEF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..3].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 5);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xF0, 0x00, 0x00, 0x00, 0x02]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..4].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len5() {
+        let val = MAX_LEN5;
+        assert_eq!(encoded_len(val), 5);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xF7, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..4].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 6);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xF8, 0x00, 0x00, 0x00, 0x00, 0x02]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..5].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len6() {
+        let val = MAX_LEN6;
+        assert_eq!(encoded_len(val), 6);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xFB, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..5].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 7);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xFC, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..6].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len7() {
+        let val = MAX_LEN7;
+        assert_eq!(encoded_len(val), 7);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..6].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 8);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(
+            encoded_slice,
+            &[0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02]
+        );
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..7].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_max_len8() {
+        let val = MAX_LEN8;
+        assert_eq!(encoded_len(val), 8);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(
+            encoded_slice,
+            &[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..7].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = val + 1;
+        assert_eq!(encoded_len(val), 9);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(
+            encoded_slice,
+            &[0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]
+        );
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..8].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_len_6() {
+        let val = 0x0300_0000_0000;
+        assert_eq!(encoded_len(val), 6);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xf8, 0x00, 0x00, 0x00, 0x00, 0xc0]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..5].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_len_7() {
+        let val = 0x1_0000_0000_0000;
+        assert_eq!(encoded_len(val), 7);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(encoded_slice, &[0xfc, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80]);
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..6].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_len_8() {
+        let val = 0x00f0_0000_0000_0000;
+        assert_eq!(encoded_len(val), 8);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(
+            encoded_slice,
+            &[0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0]
+        );
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..7].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_len_9() {
+        let val = 0x0100_0000_0000_0000;
+        assert_eq!(encoded_len(val), 9);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(
+            encoded_slice,
+            &[0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]
+        );
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..8].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_u64_2 {
+    use super::{decode, decode2, decode3, encode, encoded_len};
+    #[test]
+    fn enc_dec_all() {
+        let mut val: u64 = 1;
+        for _i in 0..64 {
+            val = (val << 1) | 0x01;
+            let encoded = encode(val);
+            let encoded_slice = encoded.as_ref();
+            assert_eq!(decode(encoded_slice).unwrap(), val);
+            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+            let mut buf = [0u8; 8];
+            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+            assert_eq!(
+                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+                val
+            );
+        }
+    }
+    #[test]
+    fn enc_dec_all_1byte() {
+        let f = |val| {
+            assert_eq!(encoded_len(val), 1);
+            let encoded = encode(val);
+            let encoded_slice = encoded.as_ref();
+            assert_eq!(decode(encoded_slice).unwrap(), val);
+            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+            let mut buf = [0u8; 8];
+            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+            assert_eq!(
+                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+                val
+            );
+        };
+        if cfg!(miri) {
+            for val in [0, 127].into_iter() {
+                f(val);
+            }
+        } else {
+            for val in 0..128 {
+                f(val);
+            }
+        };
+    }
+    #[test]
+    fn enc_dec_all_2byte() {
+        let f = |val| {
+            assert_eq!(encoded_len(val), 2);
+            let encoded = encode(val);
+            let encoded_slice = encoded.as_ref();
+            assert_eq!(decode(encoded_slice).unwrap(), val);
+            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+            let mut buf = [0u8; 8];
+            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+            assert_eq!(
+                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+                val
+            );
+        };
+        if cfg!(miri) {
+            for val in [128, 16383].into_iter() {
+                f(val);
+            }
+        } else {
+            for val in 128..16384 {
+                f(val);
+            }
+        };
+    }
+    #[test]
+    fn enc_dec_all_3byte() {
+        let f = |val| {
+            assert_eq!(encoded_len(val), 3);
+            let encoded = encode(val);
+            let encoded_slice = encoded.as_ref();
+            assert_eq!(decode(encoded_slice).unwrap(), val);
+            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+            let mut buf = [0u8; 8];
+            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+            assert_eq!(
+                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+                val
+            );
+        };
+        if cfg!(miri) {
+            for val in [16384, 2097151].into_iter() {
+                f(val);
+            }
+        } else {
+            for val in 16384..2097152 {
+                f(val);
+            }
+        };
+    }
+    #[test]
+    fn enc_dec_sp1() {
+        let val = 0x06_6C80;
+        assert_eq!(encoded_len(val), 3);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn enc_dec_sp2() {
+        let val = 136;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = 288;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = 569;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = 1144;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = 2296;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = 4601;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+        //
+        let val = 9209;
+        assert_eq!(encoded_len(val), 2);
+        let encoded = encode(val);
+        let encoded_slice = encoded.as_ref();
+        assert_eq!(decode(encoded_slice).unwrap(), val);
+        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
+        let mut buf = [0u8; 8];
+        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(
+            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
+            val
+        );
+    }
+    #[test]
+    fn decode_err_truncated() {
+        let slice = [0xF0].as_ref();
+        assert!(decode(slice).is_err());
+        assert!(decode2(slice[0], &slice[1..]).is_err());
+        let slice = [0xF8, 0x0f, 0xff].as_ref();
+        assert!(decode(slice).is_err());
+        assert!(decode2(slice[0], &slice[1..]).is_err());
+        //assert!(decode3(slice[0], 0).is_err());
+    }
+    #[test]
+    fn decode_err_leading_ones() {
+        let slice = [0xF8, 0x00, 0x00, 0x00].as_ref();
+        assert!(decode(slice).is_err());
+        assert!(decode2(slice[0], &slice[1..]).is_err());
+        //assert!(decode3(slice[0], 0).is_err());
+    }
+}
+
 #[cfg(test)]
-mod test_u64_0 {
-    use super::{decode, decode2, decode3, encode};
-    //
+mod test_u64_3 {
+    use super::{check_result_with_length, decode, encode, Error, Vu64};
     #[test]
-    fn encode_bit_pattern_examples() {
-        assert_eq!(encode(0x0f0f).as_ref(), &[0x8F, 0x3c]);
-        assert_eq!(encode(0x0f0f_f0f0).as_ref(), &[0xE0, 0x0f, 0xff, 0xf0]);
+    fn vu64_debug_format_1() {
+        let vu64 = encode(123456789);
+        assert_eq!(format!("{vu64:#?}"), "V64(123456789)");
+    }
+    #[test]
+    fn try_from_1() {
+        let v = vec![0xE0u8, 0x0fu8, 0xffu8, 0xf0u8];
+        let r = Vu64::try_from(v.as_slice());
+        assert!(r.is_ok());
+        assert_eq!(format!("{:#?}", r.unwrap()), "V64(252702960)");
+    }
+    #[test]
+    fn try_into_1() {
+        let v = vec![0xE0u8, 0x0fu8, 0xffu8, 0xf0u8];
+        let r: Result<Vu64, Error> = v.as_slice().try_into();
+        assert!(r.is_ok());
+        assert_eq!(format!("{:#?}", r.unwrap()), "V64(252702960)");
+    }
+    #[test]
+    fn decode_empty_1() {
+        let v: Vec<u8> = Vec::new();
+        let r = decode(v.as_slice());
+        assert!(r.is_err());
+        assert_eq!(r, Err(Error::Truncated));
+    }
+    #[test]
+    fn check_result_with_length_1() {
+        let r = check_result_with_length(2, 123456789);
+        assert!(r.is_ok());
+        let r = check_result_with_length(9, 123456789);
+        assert!(r.is_err());
+        assert_eq!(r, Err(Error::LeadingOnes));
+    }
+    #[test]
+    fn error_format_1() {
+        let v: Vec<u8> = Vec::new();
+        let r = decode(v.as_slice());
+        assert!(r.is_err());
+        assert_eq!(r, Err(Error::Truncated));
+        if let Err(err) = r {
+            assert_eq!(format!("{err}"), "truncated vu64 value");
+        }
+        let r = check_result_with_length(9, 123456789);
+        assert!(r.is_err());
+        assert_eq!(r, Err(Error::LeadingOnes));
+        if let Err(err) = r {
+            assert_eq!(format!("{err}"), "leading ones in vu64 value");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_detect_format {
+    use super::{detect_format, pack, FormatGuess};
+    #[test]
+    fn detect_vu64() {
+        let buf = pack(&[0x0f0f, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX]);
+        assert_eq!(detect_format(&buf), FormatGuess::Vu64);
+    }
+    #[test]
+    fn detect_vint64() {
+        // vint64: 127 => [0xFF], 300 => [0xB2, 0x04], u64::MAX => [0x00, 0xFF x 8]
+        assert_eq!(detect_format(&[0xFF]), FormatGuess::Vint64);
+        assert_eq!(detect_format(&[0xB2, 0x04, 0xFF]), FormatGuess::Vint64);
+        let buf = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(detect_format(&buf), FormatGuess::Vint64);
+    }
+    #[test]
+    fn detect_leb128() {
+        // LEB128: 128 => [0x80, 0x01], 16384 => [0x80, 0x80, 0x01]
+        assert_eq!(detect_format(&[0x80, 0x01]), FormatGuess::Leb128);
         assert_eq!(
-            encode(0x0f0f_f0f0_0f0f).as_ref(),
-            &[0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07]
+            detect_format(&[0x80, 0x01, 0x80, 0x80, 0x01]),
+            FormatGuess::Leb128
+        );
+    }
+    #[test]
+    fn detect_unknown() {
+        assert_eq!(detect_format(&[]), FormatGuess::Unknown);
+        assert_eq!(detect_format(&[0xFE]), FormatGuess::Unknown);
+    }
+    #[test]
+    fn detect_ambiguous_prefers_vu64() {
+        // single bytes under 0x80 are valid in every format
+        assert_eq!(detect_format(&[0x01, 0x03, 0x05]), FormatGuess::Vu64);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_merge_sorted {
+    use super::{merge_sorted, pack, unpack, Error};
+    #[test]
+    fn merge_sorted_1() {
+        let a = [0, 5, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let b = [1, 5, 16384, 0x0f0f_f0f0_0f0f];
+        let mut out = Vec::new();
+        merge_sorted(&pack(&a), &pack(&b), &mut out).unwrap();
+        let merged = unpack(&out).unwrap();
+        let mut expected: Vec<u64> = a.iter().chain(b.iter()).copied().collect();
+        expected.sort_unstable();
+        assert_eq!(merged, expected);
+        assert!(merged.windows(2).all(|w| w[0] <= w[1]));
+    }
+    #[test]
+    fn merge_sorted_empty() {
+        let a = [3, 4];
+        let mut out = Vec::new();
+        merge_sorted(&pack(&a), &[], &mut out).unwrap();
+        assert_eq!(unpack(&out), Ok(a.to_vec()));
+        let mut out = Vec::new();
+        merge_sorted(&[], &pack(&a), &mut out).unwrap();
+        assert_eq!(unpack(&out), Ok(a.to_vec()));
+        let mut out = Vec::new();
+        merge_sorted(&[], &[], &mut out).unwrap();
+        assert!(out.is_empty());
+    }
+    #[test]
+    fn merge_sorted_err_truncated() {
+        let a = pack(&[1, 0x0f0f_f0f0]);
+        let b = pack(&[2]);
+        let mut out = Vec::new();
+        let r = merge_sorted(&a[..a.len() - 1], &b, &mut out);
+        assert_eq!(r, Err(Error::Truncated));
+    }
+}
+
+#[cfg(test)]
+mod test_vu64_checked {
+    use super::{encode, MAX_LEN1, MAX_LEN8};
+    #[test]
+    fn checked_add_1() {
+        assert_eq!(encode(0).checked_add(0), Some(encode(0)));
+        assert_eq!(encode(MAX_LEN1).checked_add(1), Some(encode(MAX_LEN1 + 1)));
+        assert_eq!(encode(MAX_LEN8).checked_add(1), Some(encode(MAX_LEN8 + 1)));
+        assert_eq!(encode(u64::MAX - 1).checked_add(1), Some(encode(u64::MAX)));
+        assert_eq!(
+            encode(MAX_LEN1 + 1).checked_add(1).unwrap().as_ref().len(),
+            2
         );
+    }
+    #[test]
+    fn checked_add_overflow() {
+        assert_eq!(encode(u64::MAX).checked_add(1), None);
+        assert_eq!(encode(1).checked_add(u64::MAX), None);
+    }
+    #[test]
+    fn checked_sub_1() {
+        assert_eq!(encode(MAX_LEN1 + 1).checked_sub(1), Some(encode(MAX_LEN1)));
         assert_eq!(
-            encode(0x0f0f_f0f0_0f0f_f0f0).as_ref(),
-            &[0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f]
+            encode(MAX_LEN1 + 1).checked_sub(1).unwrap().as_ref().len(),
+            1
+        );
+        assert_eq!(encode(u64::MAX).checked_sub(u64::MAX), Some(encode(0)));
+    }
+    #[test]
+    fn checked_sub_underflow() {
+        assert_eq!(encode(0).checked_sub(1), None);
+        assert_eq!(encode(MAX_LEN1).checked_sub(MAX_LEN1 + 1), None);
+    }
+    #[test]
+    fn incremented_1() {
+        assert_eq!(encode(0).incremented(), Some(encode(1)));
+        assert_eq!(encode(MAX_LEN8).incremented(), Some(encode(MAX_LEN8 + 1)));
+        assert_eq!(encode(u64::MAX - 1).incremented(), Some(encode(u64::MAX)));
+        assert_eq!(encode(u64::MAX).incremented(), None);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_until_zero {
+    use super::{decode_until_zero, pack, Error};
+    #[test]
+    fn decode_until_zero_1() {
+        let buf = pack(&[1, 0x0f0f, 0x0f0f_f0f0_0f0f_f0f0, 0, 5]);
+        let mut values = Vec::new();
+        let r = decode_until_zero(&buf, &mut values);
+        assert_eq!(r, Ok(1 + 2 + 9 + 1));
+        assert_eq!(values, vec![1, 0x0f0f, 0x0f0f_f0f0_0f0f_f0f0]);
+    }
+    #[test]
+    fn decode_until_zero_empty_body() {
+        let mut values = Vec::new();
+        assert_eq!(decode_until_zero(&[0x00], &mut values), Ok(1));
+        assert!(values.is_empty());
+    }
+    #[test]
+    fn decode_until_zero_err_truncated() {
+        let mut values = Vec::new();
+        let r = decode_until_zero(&[0x01, 0x8F, 0x3c], &mut values);
+        assert_eq!(r, Err(Error::Truncated));
+        let mut values = Vec::new();
+        let r = decode_until_zero(&[], &mut values);
+        assert_eq!(r, Err(Error::Truncated));
+        let mut values = Vec::new();
+        let r = decode_until_zero(&[0x01, 0x8F], &mut values);
+        assert_eq!(r, Err(Error::Truncated));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_fixed_len_block {
+    use super::{decode_fixed_len_block, pack, Error, MAX_LEN3, MAX_LEN4};
+    #[test]
+    fn decode_fixed_len_block_1() {
+        let values = [MAX_LEN3 + 1, 0x0f0f_f0f0, MAX_LEN4];
+        let buf = pack(&values);
+        assert_eq!(buf.len(), 12);
+        let mut out = Vec::new();
+        assert_eq!(decode_fixed_len_block(&buf, 4, 3, &mut out), Ok(12));
+        assert_eq!(out, values);
+        //
+        let mut out = Vec::new();
+        assert_eq!(decode_fixed_len_block(&buf, 4, 2, &mut out), Ok(8));
+        assert_eq!(out, &values[..2]);
+        //
+        let mut out = Vec::new();
+        assert_eq!(decode_fixed_len_block(&buf, 4, 0, &mut out), Ok(0));
+        assert!(out.is_empty());
+    }
+    #[test]
+    fn decode_fixed_len_block_1byte() {
+        let mut out = Vec::new();
+        let r = decode_fixed_len_block(&[0x00, 0x01, 0x7F], 1, 3, &mut out);
+        assert_eq!(r, Ok(3));
+        assert_eq!(out, vec![0, 1, 127]);
+    }
+    #[test]
+    fn decode_fixed_len_block_err_truncated() {
+        let buf = pack(&[0x0f0f_f0f0, 0x0f0f_f0f0]);
+        let mut out = Vec::new();
+        let r = decode_fixed_len_block(&buf[..7], 4, 2, &mut out);
+        assert_eq!(r, Err(Error::Truncated));
+        assert!(out.is_empty());
+    }
+    #[test]
+    fn decode_fixed_len_block_err_count_overflow() {
+        let mut out = Vec::new();
+        let r = decode_fixed_len_block(&[0x8F, 0x3c], 2, usize::MAX, &mut out);
+        assert_eq!(r, Err(Error::Truncated));
+        assert!(out.is_empty());
+    }
+    #[test]
+    fn decode_fixed_len_block_err_width() {
+        let buf = pack(&[0x0f0f_f0f0, 0x0f0f]);
+        let mut out = Vec::new();
+        let r = decode_fixed_len_block(&buf, 3, 2, &mut out);
+        assert_eq!(r, Err(Error::LeadingOnes));
+        let r = decode_fixed_len_block(&buf, 0, 2, &mut out);
+        assert_eq!(r, Err(Error::LeadingOnes));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_to_vu64s {
+    use super::{decode, decode_to_vu64s, encode, Error};
+    #[test]
+    fn decode_to_vu64s_1() {
+        let values = [0, 0x0f0f, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let mut buf = Vec::new();
+        let mut offsets = Vec::new();
+        for &val in values.iter() {
+            offsets.push(buf.len());
+            buf.extend_from_slice(encode(val).as_ref());
+        }
+        offsets.push(buf.len());
+        let mut out = Vec::new();
+        assert_eq!(decode_to_vu64s(&buf, &mut out), Ok(values.len()));
+        for (i, vu64) in out.iter().enumerate() {
+            assert_eq!(vu64.as_ref(), &buf[offsets[i]..offsets[i + 1]]);
+            assert_eq!(*vu64, encode(values[i]));
+            assert_eq!(decode(vu64.as_ref()), Ok(values[i]));
+        }
+    }
+    #[test]
+    fn decode_to_vu64s_err() {
+        let mut out = Vec::new();
+        let r = decode_to_vu64s(&[0x01, 0x8F], &mut out);
+        assert_eq!(r, Err(Error::Truncated));
+        let mut out = Vec::new();
+        let r = decode_to_vu64s(&[0x01, 0xDD, 0, 0], &mut out);
+        assert_eq!(r, Err(Error::RedundantEncode));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_adaptive {
+    use super::{decode_adaptive, encode, encode_adaptive, Error};
+    fn round_trip(values: &[u64]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_adaptive(values, &mut buf);
+        let mut out = Vec::new();
+        assert_eq!(decode_adaptive(&buf, &mut out), Ok(values.len()));
+        assert_eq!(out, values);
+        buf
+    }
+    fn raw_len(values: &[u64]) -> usize {
+        values.iter().map(|&v| encode(v).as_ref().len()).sum()
+    }
+    #[test]
+    fn adaptive_favors_delta() {
+        let values: Vec<u64> = (0..20).map(|i| 0x0f0f_f0f0_0f0f + i * 3).collect();
+        let buf = round_trip(&values);
+        // 3 tag bytes, 1 raw of 7 bytes, 19 deltas of 1 byte
+        assert_eq!(buf.len(), 3 + 7 + 19);
+        assert!(buf.len() < raw_len(&values));
+        assert_eq!(buf[0], 0b1111_1110);
+    }
+    #[test]
+    fn adaptive_favors_raw() {
+        let values = [5, 0x0f0f_f0f0_0f0f, 7, 0x0f0f_f0f0, 0, 100, 1];
+        let buf = round_trip(&values);
+        assert_eq!(buf.len(), 1 + raw_len(&values));
+        assert_eq!(buf[0], 0);
+    }
+    #[test]
+    fn adaptive_descending_and_wrapping() {
+        round_trip(&[u64::MAX, u64::MAX - 1, 0, u64::MAX, 1_000_000, 999_990]);
+    }
+    #[test]
+    fn adaptive_empty() {
+        let buf = round_trip(&[]);
+        assert!(buf.is_empty());
+    }
+    #[test]
+    fn adaptive_group_boundary() {
+        let values: Vec<u64> = (0..16).map(|i| 1000 + i).collect();
+        let buf = round_trip(&values);
+        assert_eq!(buf.len(), 2 + 2 + 15);
+    }
+    #[test]
+    fn adaptive_err_truncated() {
+        let mut buf = Vec::new();
+        encode_adaptive(&[1000, 0x0f0f_f0f0], &mut buf);
+        let mut out = Vec::new();
+        let r = decode_adaptive(&buf[..buf.len() - 1], &mut out);
+        assert_eq!(r, Err(Error::Truncated));
+        let mut out = Vec::new();
+        let r = decode_adaptive(&buf[..1], &mut out);
+        assert_eq!(r, Err(Error::Truncated));
+    }
+}
+
+#[cfg(test)]
+mod test_decode2_exact {
+    use super::{decode2, decode2_exact, encode, Error};
+    #[test]
+    fn decode2_exact_1() {
+        for val in [0, 127, 0x0f0f, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX] {
+            let encoded = encode(val);
+            let slice = encoded.as_ref();
+            assert_eq!(decode2_exact(slice[0], &slice[1..]), Ok(val));
+        }
+    }
+    #[test]
+    fn decode2_exact_short() {
+        let slice = [0xE0, 0x0f, 0xff, 0xf0].as_ref();
+        assert_eq!(decode2_exact(slice[0], &slice[1..3]), Err(Error::Truncated));
+        assert_eq!(decode2(slice[0], &slice[1..3]), Err(Error::Truncated));
+    }
+    #[test]
+    fn decode2_exact_over_long() {
+        let slice = [0xE0, 0x0f, 0xff, 0xf0, 0x01].as_ref();
+        assert_eq!(
+            decode2_exact(slice[0], &slice[1..]),
+            Err(Error::TrailingBytes)
+        );
+        // `decode2()` ignores the trailing bytes
+        assert_eq!(decode2(slice[0], &slice[1..]), Ok(0x0f0f_f0f0));
+        let slice = [0x7F, 0x00].as_ref();
+        assert_eq!(
+            decode2_exact(slice[0], &slice[1..]),
+            Err(Error::TrailingBytes)
+        );
+        assert_eq!(decode2(slice[0], &slice[1..]), Ok(0x7F));
+    }
+    #[test]
+    fn error_format_trailing_bytes() {
+        assert_eq!(
+            format!("{}", Error::TrailingBytes),
+            "trailing bytes after vu64 value"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_parsed_buffer {
+    use super::{pack, Error, ParsedBuffer};
+    #[test]
+    fn parsed_buffer_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let buf = pack(&values);
+        let parsed = ParsedBuffer::new(&buf).unwrap();
+        assert_eq!(parsed.len(), values.len());
+        assert!(!parsed.is_empty());
+        assert_eq!(parsed.as_bytes(), buf.as_slice());
+        assert_eq!(parsed.iter().len(), values.len());
+        // scan it twice
+        assert_eq!(parsed.iter().collect::<Vec<_>>(), values);
+        assert_eq!((&parsed).into_iter().collect::<Vec<_>>(), values);
+        for (i, &val) in values.iter().enumerate() {
+            assert_eq!(parsed.get(i), Some(val));
+        }
+        assert_eq!(parsed.get(values.len()), None);
+    }
+    #[test]
+    fn parsed_buffer_indexed_iter() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let buf = pack(&values);
+        let parsed = ParsedBuffer::new(&buf).unwrap();
+        assert_eq!(parsed.indexed_iter().len(), values.len());
+        assert_eq!(parsed.indexed_iter().collect::<Vec<_>>(), values);
+        let mut reversed = values.to_vec();
+        reversed.reverse();
+        assert_eq!(parsed.indexed_iter().rev().collect::<Vec<_>>(), reversed);
+        // from both ends
+        let mut iter = parsed.indexed_iter();
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next_back(), Some(u64::MAX));
+        assert_eq!(iter.next_back(), Some(0x0f0f_f0f0_0f0f));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![127, 128, 0x0f0f_f0f0]);
+    }
+    #[test]
+    fn parsed_buffer_empty() {
+        let parsed = ParsedBuffer::new(&[]).unwrap();
+        assert!(parsed.is_empty());
+        assert_eq!(parsed.iter().next(), None);
+        assert_eq!(parsed.indexed_iter().next_back(), None);
+    }
+    #[test]
+    fn parsed_buffer_err() {
+        let r = ParsedBuffer::new(&[0x01, 0xE0, 0x0f, 0xff]);
+        assert_eq!(r.unwrap_err(), Error::Truncated);
+        let r = ParsedBuffer::new(&[0x01, 0xDD, 0x00, 0x00]);
+        assert_eq!(r.unwrap_err(), Error::RedundantEncode);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_at {
+    use super::{decode_at, encode, pack, Error};
+    #[test]
+    fn decode_at_walk() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let buf = pack(&values);
+        let mut pos = 0;
+        for &val in values.iter() {
+            let start = pos;
+            assert_eq!(decode_at(&buf, &mut pos), Ok(val));
+            assert_eq!(pos - start, encode(val).as_ref().len());
+        }
+        assert_eq!(pos, buf.len());
+        assert_eq!(decode_at(&buf, &mut pos), Err(Error::Truncated));
+        assert_eq!(pos, buf.len());
+    }
+    #[test]
+    fn decode_at_err_not_advance() {
+        let buf = [0x01, 0xE0, 0x0f, 0xff];
+        let mut pos = 1;
+        assert_eq!(decode_at(&buf, &mut pos), Err(Error::Truncated));
+        assert_eq!(pos, 1);
+        let buf = [0x01, 0xDD, 0x00, 0x00];
+        assert_eq!(decode_at(&buf, &mut pos), Err(Error::RedundantEncode));
+        assert_eq!(pos, 1);
+        let mut pos = 10;
+        assert_eq!(decode_at(&buf, &mut pos), Err(Error::Truncated));
+        assert_eq!(pos, 10);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_checksum {
+    use super::{decode_with_checksum, encode, encode_with_checksum, Error};
+    #[test]
+    fn checksum_round_trip() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let mut buf = Vec::new();
+        for &val in values.iter() {
+            encode_with_checksum(val, &mut buf);
+        }
+        let mut rest = buf.as_slice();
+        for &val in values.iter() {
+            let (v, len) = decode_with_checksum(rest).unwrap();
+            assert_eq!(v, val);
+            assert_eq!(len, encode(val).as_ref().len() + 1);
+            rest = &rest[len..];
+        }
+        assert!(rest.is_empty());
+    }
+    #[test]
+    fn checksum_err_flipped_bit() {
+        let mut buf = Vec::new();
+        encode_with_checksum(0x0f0f_f0f0, &mut buf);
+        for i in 1..buf.len() {
+            let mut corrupted = buf.clone();
+            corrupted[i] ^= 0x04;
+            assert_eq!(
+                decode_with_checksum(&corrupted),
+                Err(Error::ChecksumMismatch)
+            );
+        }
+    }
+    #[test]
+    fn checksum_err_truncated() {
+        let mut buf = Vec::new();
+        encode_with_checksum(0x0f0f, &mut buf);
+        assert_eq!(decode_with_checksum(&buf[..2]), Err(Error::Truncated));
+        assert_eq!(decode_with_checksum(&buf[..1]), Err(Error::Truncated));
+    }
+    #[test]
+    fn error_format_checksum_mismatch() {
+        assert_eq!(
+            format!("{}", Error::ChecksumMismatch),
+            "checksum mismatch of vu64 value"
         );
     }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_build_offset_index {
+    use super::{build_offset_index, decode, pack, Error};
+    #[test]
+    fn build_offset_index_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX, 5];
+        let buf = pack(&values);
+        let index = build_offset_index(&buf).unwrap();
+        assert_eq!(index, vec![0, 1, 2, 4, 8, 15, 24]);
+        for (i, &val) in values.iter().enumerate() {
+            assert_eq!(decode(&buf[index[i]..]), Ok(val));
+        }
+        // the last value without a forward scan
+        assert_eq!(decode(&buf[*index.last().unwrap()..]), Ok(5));
+    }
+    #[test]
+    fn build_offset_index_empty() {
+        assert_eq!(build_offset_index(&[]), Ok(vec![]));
+    }
+    #[test]
+    fn build_offset_index_err() {
+        assert_eq!(build_offset_index(&[0x01, 0x8F]), Err(Error::Truncated));
+        assert_eq!(
+            build_offset_index(&[0x01, 0xDD, 0x00, 0x00]),
+            Err(Error::RedundantEncode)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_encoded_len_const {
+    use super::{encode_bounded, encoded_len, encoded_len_const};
+    use super::{MAX_LEN1, MAX_LEN2, MAX_LEN3, MAX_LEN4, MAX_LEN5, MAX_LEN6, MAX_LEN7, MAX_LEN8};
+    const LEN_MAX_LEN3: u8 = encoded_len_const(MAX_LEN3);
+    const _: () = assert!(encoded_len_const(MAX_LEN3) <= 3);
+    #[test]
+    fn encoded_len_const_boundaries() {
+        assert_eq!(LEN_MAX_LEN3, 3);
+        for &val in [
+            0,
+            MAX_LEN1,
+            MAX_LEN2,
+            MAX_LEN3,
+            MAX_LEN4,
+            MAX_LEN5,
+            MAX_LEN6,
+            MAX_LEN7,
+            MAX_LEN8,
+            u64::MAX,
+        ]
+        .iter()
+        {
+            assert_eq!(encoded_len_const(val), encoded_len(val), "val: {val}");
+            let val = val.wrapping_add(1);
+            assert_eq!(encoded_len_const(val), encoded_len(val), "val: {val}");
+        }
+        let mut val: u64 = 1;
+        for _i in 0..64 {
+            assert_eq!(encoded_len_const(val), encoded_len(val), "val: {val}");
+            val = (val << 1) | 0x01;
+        }
+    }
+    #[test]
+    fn encode_bounded_1() {
+        assert_eq!(encode_bounded::<MAX_LEN2>(MAX_LEN2).as_ref(), &[0xBF, 0xFF]);
+        assert_eq!(encode_bounded::<{ u64::MAX }>(0).as_ref(), &[0x00]);
+    }
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn encode_bounded_over() {
+        let _ = encode_bounded::<MAX_LEN2>(MAX_LEN2 + 1);
+    }
+}
+
+#[cfg(test)]
+mod test_decode_small {
+    use super::{decode, decode_small, Error};
+    #[test]
+    fn decode_small_1() {
+        assert_eq!(decode_small(0x00), Ok(0));
+        assert_eq!(decode_small(0x01), Ok(1));
+        assert_eq!(decode_small(0x7F), Ok(127));
+        for byte in 0..0x80u8 {
+            assert_eq!(decode_small(byte), decode(&[byte]));
+        }
+    }
     #[test]
-    fn decode_bit_pattern_examples() {
-        let slice = [0x8F, 0x3c].as_ref();
-        assert_eq!(decode(slice).unwrap(), 0x0f0f);
-
-        let slice = [0xE0, 0x0f, 0xff, 0xf0].as_ref();
-        assert_eq!(decode(slice).unwrap(), 0x0f0f_f0f0);
-
-        let slice = [0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07].as_ref();
-        assert_eq!(decode(slice).unwrap(), 0x0f0f_f0f0_0f0f);
+    fn decode_small_err() {
+        assert_eq!(decode_small(0x80), Err(Error::Truncated));
+        assert_eq!(decode_small(0xFF), Err(Error::Truncated));
+    }
+}
 
-        let slice = [0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f].as_ref();
-        assert_eq!(decode(slice).unwrap(), 0x0f0f_f0f0_0f0f_f0f0);
+#[cfg(all(test, feature = "alloc"))]
+mod test_remove_value {
+    use super::{pack, remove_value, Error};
+    #[test]
+    fn remove_value_present() {
+        let buf = pack(&[0, 0x0f0f_f0f0, 127, 0x0f0f_f0f0, u64::MAX]);
+        let mut out = Vec::new();
+        assert_eq!(remove_value(&buf, 0x0f0f_f0f0, &mut out), Ok(true));
+        assert_eq!(out, pack(&[0, 127, 0x0f0f_f0f0, u64::MAX]));
+        //
+        let mut out = Vec::new();
+        assert_eq!(remove_value(&buf, u64::MAX, &mut out), Ok(true));
+        assert_eq!(out, pack(&[0, 0x0f0f_f0f0, 127, 0x0f0f_f0f0]));
     }
     #[test]
-    fn decode2_bit_pattern_examples() {
-        let slice = [0x8F, 0x3c].as_ref();
-        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), 0x0f0f);
-
-        let slice = [0xE0, 0x0f, 0xff, 0xf0].as_ref();
-        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), 0x0f0f_f0f0);
-
-        let slice = [0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07].as_ref();
-        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), 0x0f0f_f0f0_0f0f);
-
-        let slice = [0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f].as_ref();
-        assert_eq!(
-            decode2(slice[0], &slice[1..]).unwrap(),
-            0x0f0f_f0f0_0f0f_f0f0
-        );
+    fn remove_value_absent() {
+        let buf = pack(&[0, 0x0f0f_f0f0, 127]);
+        let mut out = Vec::new();
+        assert_eq!(remove_value(&buf, 128, &mut out), Ok(false));
+        assert_eq!(out, buf);
+        //
+        let mut out = Vec::new();
+        assert_eq!(remove_value(&[], 0, &mut out), Ok(false));
+        assert!(out.is_empty());
     }
     #[test]
-    fn decode3_bit_pattern_examples() {
-        let slice = [0x8F, 0x3c].as_ref();
-        let mut buf = [0u8; 8];
-        buf[..1].copy_from_slice(&slice[1..]);
-        assert_eq!(decode3(slice[0], u64::from_le_bytes(buf)).unwrap(), 0x0f0f);
-
-        let slice = [0xE0, 0x0f, 0xff, 0xf0].as_ref();
-        let mut buf = [0u8; 8];
-        buf[..3].copy_from_slice(&slice[1..]);
+    fn remove_value_err() {
+        let mut out = Vec::new();
         assert_eq!(
-            decode3(slice[0], u64::from_le_bytes(buf)).unwrap(),
-            0x0f0f_f0f0
+            remove_value(&[0x01, 0xDD, 0x00, 0x00], 1, &mut out),
+            Err(Error::RedundantEncode)
         );
-
-        let slice = [0xFD, 0x87, 0x07, 0x78, 0xf8, 0x87, 0x07].as_ref();
-        let mut buf = [0u8; 8];
-        buf[..6].copy_from_slice(&slice[1..]);
+        let mut out = Vec::new();
         assert_eq!(
-            decode3(slice[0], u64::from_le_bytes(buf)).unwrap(),
-            0x0f0f_f0f0_0f0f
+            remove_value(&[0x01, 0x8F], 1, &mut out),
+            Err(Error::Truncated)
         );
+    }
+}
 
-        let slice = [0xFF, 0xf0, 0xf0, 0x0f, 0x0f, 0xf0, 0xf0, 0x0f, 0x0f].as_ref();
-        let mut buf = [0u8; 8];
-        buf[..8].copy_from_slice(&slice[1..]);
-        assert_eq!(
-            decode3(slice[0], u64::from_le_bytes(buf)).unwrap(),
-            0x0f0f_f0f0_0f0f_f0f0
-        );
+#[cfg(all(test, feature = "alloc"))]
+mod test_sorted_set {
+    use super::{decode_sorted_set, encode, encode_sorted_set, Error, MAX_SORTED_SET_LEN};
+    fn round_trip(values: &[u64]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_sorted_set(values, &mut buf);
+        let mut out = Vec::new();
+        assert_eq!(decode_sorted_set(&buf, &mut out), Ok(values.len()));
+        assert_eq!(out, values);
+        buf
     }
     #[test]
-    fn encode_decode() {
-        let val = 1234;
-        assert_eq!(decode(encode(val).as_ref()).unwrap(), val);
-        let val = 123456789;
-        assert_eq!(decode(encode(val).as_ref()).unwrap(), val);
+    fn sorted_set_long_runs() {
+        let mut values: Vec<u64> = (1_000..2_000).collect();
+        values.extend(5_000..5_500);
+        values.push(10_000);
+        values.extend(0x0f0f_f0f0..0x0f0f_f0f0 + 300);
+        let buf = round_trip(&values);
+        // 4 runs of at most 4 + 2 bytes
+        assert!(buf.len() <= 4 * 6, "buf.len(): {}", buf.len());
     }
     #[test]
-    fn encode_decode2() {
-        let val = 1234;
-        let enc = encode(val);
-        let slice = enc.as_ref();
-        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), val);
-        let val = 123456789;
-        let enc = encode(val);
-        let slice = enc.as_ref();
-        assert_eq!(decode2(slice[0], &slice[1..]).unwrap(), val);
+    fn sorted_set_sparse() {
+        let values = [0, 2, 200, 0x0f0f, 0x0f0f_f0f0, u64::MAX - 1];
+        let buf = round_trip(&values);
+        assert_eq!(buf.len(), 1 + 1 + 2 + 2 + 4 + 9 + values.len());
     }
     #[test]
-    fn encode_decode3() {
-        let val = 1234;
-        let enc = encode(val);
-        let slice = enc.as_ref();
-        let mut buf = [0u8; 8];
-        buf[..1].copy_from_slice(&slice[1..]);
-        assert_eq!(decode3(slice[0], u64::from_le_bytes(buf)).unwrap(), val);
-        let val = 123456789;
-        let enc = encode(val);
-        let slice = enc.as_ref();
-        let mut buf = [0u8; 8];
-        buf[..3].copy_from_slice(&slice[1..]);
-        assert_eq!(decode3(slice[0], u64::from_le_bytes(buf)).unwrap(), val);
+    fn sorted_set_edge() {
+        round_trip(&[]);
+        round_trip(&[0]);
+        round_trip(&[u64::MAX - 2, u64::MAX - 1, u64::MAX]);
+    }
+    #[test]
+    fn sorted_set_wraparound() {
+        // a run does not continue from u64::MAX to 0
+        round_trip(&[u64::MAX, 0]);
+        round_trip(&[u64::MAX - 1, u64::MAX, 0, 1]);
+    }
+    #[test]
+    fn sorted_set_err_truncated() {
+        let mut out = Vec::new();
+        assert_eq!(
+            decode_sorted_set(&[1, 3, 6], &mut out),
+            Err(Error::Truncated)
+        );
+    }
+    #[test]
+    fn sorted_set_err_malicious_run() {
+        // a run of about u64::MAX values in 10 bytes
+        let mut buf = encode(0).as_ref().to_vec();
+        buf.extend_from_slice(encode(u64::MAX - 1).as_ref());
+        let mut out = Vec::new();
+        assert_eq!(decode_sorted_set(&buf, &mut out), Err(Error::TooManyValues));
+        assert!(out.is_empty());
+        // a run going beyond u64::MAX
+        let mut buf = encode(u64::MAX - 1).as_ref().to_vec();
+        buf.extend_from_slice(encode(2).as_ref());
+        assert_eq!(decode_sorted_set(&buf, &mut out), Err(Error::NotMonotonic));
+        assert!(out.is_empty());
+    }
+    #[test]
+    fn sorted_set_err_over_limit() {
+        let max = MAX_SORTED_SET_LEN as u64;
+        // exactly the limit
+        let mut buf = encode(0).as_ref().to_vec();
+        buf.extend_from_slice(encode(max - 1).as_ref());
+        let mut out = Vec::new();
+        assert_eq!(decode_sorted_set(&buf, &mut out), Ok(MAX_SORTED_SET_LEN));
+        // the limit over the runs
+        buf.extend_from_slice(&[1, 0]);
+        let mut out = Vec::new();
+        assert_eq!(decode_sorted_set(&buf, &mut out), Err(Error::TooManyValues));
     }
 }
 
 #[cfg(test)]
-mod test_u64_1 {
-    use super::{decode, decode2, decode3, encode, encoded_len};
-    use super::{MAX_LEN1, MAX_LEN2, MAX_LEN3, MAX_LEN4, MAX_LEN5, MAX_LEN6, MAX_LEN7, MAX_LEN8};
+mod test_error_code {
+    use super::Error;
     #[test]
-    fn enc_dec_max_u64() {
-        let val = u64::MAX;
-        assert_eq!(encoded_len(val), 9);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
+    fn error_code_1() {
+        assert_eq!(Error::LeadingOnes.code(), "leading_ones");
+        assert_eq!(Error::Truncated.code(), "truncated");
+        assert_eq!(Error::RedundantEncode.code(), "redundant");
+        assert_eq!(Error::TrailingBytes.code(), "trailing_bytes");
+        assert_eq!(Error::ChecksumMismatch.code(), "checksum_mismatch");
+        assert_eq!(Error::BufferFull.code(), "buffer_full");
+        assert_eq!(Error::InvalidBase64.code(), "invalid_base64");
+        assert_eq!(Error::NotMonotonic.code(), "not_monotonic");
+        assert_eq!(Error::UnknownDiscriminant.code(), "unknown_discriminant");
+        assert_eq!(Error::Unexpected.code(), "unexpected");
+        assert_eq!(Error::TooManyValues.code(), "too_many_values");
+        assert_eq!(Error::CountMismatch.code(), "count_mismatch");
+        assert_eq!(Error::InvalidWidth.code(), "invalid_width");
+    }
+    #[test]
+    fn error_as_str_1() {
+        assert_eq!(Error::LeadingOnes.as_str(), "leading ones in vu64 value");
+        assert_eq!(Error::Truncated.as_str(), "truncated vu64 value");
         assert_eq!(
-            encoded_slice,
-            &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+            Error::RedundantEncode.as_str(),
+            "redundant encoded vu64 value"
         );
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..8].copy_from_slice(&encoded_slice[1..]);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            Error::TrailingBytes.as_str(),
+            "trailing bytes after vu64 value"
+        );
+        assert_eq!(
+            Error::ChecksumMismatch.as_str(),
+            "checksum mismatch of vu64 value"
+        );
+        assert_eq!(Error::BufferFull.as_str(), "output buffer is full");
+        assert_eq!(
+            Error::InvalidBase64.as_str(),
+            "invalid base64 text of vu64 value"
+        );
+        assert_eq!(
+            Error::NotMonotonic.as_str(),
+            "out of order vu64 value in monotonic sequence"
+        );
+        assert_eq!(
+            Error::UnknownDiscriminant.as_str(),
+            "unknown enum discriminant in vu64 value"
+        );
+        assert_eq!(Error::Unexpected.as_str(), "unexpected vu64 value");
+        assert_eq!(Error::TooManyValues.as_str(), "too many vu64 values");
+        assert_eq!(
+            Error::CountMismatch.as_str(),
+            "mismatched count of vu64 values"
+        );
+        assert_eq!(
+            Error::InvalidWidth.as_str(),
+            "unsupported width of fixed integer"
         );
     }
     #[test]
-    fn enc_dec_max_u32() {
-        let val = u32::MAX.into();
-        assert_eq!(encoded_len(val), 5);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xf7, 0xff, 0xff, 0xff, 0x1f]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..4].copy_from_slice(&encoded_slice[1..]);
+    fn error_code_differs_from_display() {
+        let all = [
+            Error::LeadingOnes,
+            Error::Truncated,
+            Error::RedundantEncode,
+            Error::TrailingBytes,
+            Error::ChecksumMismatch,
+            Error::BufferFull,
+            Error::InvalidBase64,
+            Error::NotMonotonic,
+            Error::UnknownDiscriminant,
+            Error::Unexpected,
+            Error::TooManyValues,
+            Error::CountMismatch,
+            Error::InvalidWidth,
+        ];
+        for (i, a) in all.iter().enumerate() {
+            assert_ne!(a.code(), format!("{a}"));
+            assert_eq!(a.as_str(), format!("{a}"));
+            for b in all[i + 1..].iter() {
+                assert_ne!(a.code(), b.code());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_peek_in_range {
+    use super::{encode, peek_in_range, Error};
+    #[test]
+    fn peek_in_range_in() {
+        let buf = encode(0x0f0f_f0f0);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            peek_in_range(buf.as_ref(), 0x0f0f_f0f0..=0x0f0f_f0f0),
+            Ok(true)
         );
+        assert_eq!(peek_in_range(buf.as_ref(), 0..=u64::MAX), Ok(true));
+        assert_eq!(peek_in_range(&[0x00, 0xFF], 0..=0), Ok(true));
     }
     #[test]
-    fn enc_dec_max_u16() {
-        let val = u16::MAX.into();
-        assert_eq!(encoded_len(val), 3);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xdf, 0xff, 0x07]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..2].copy_from_slice(&encoded_slice[1..]);
+    fn peek_in_range_out() {
+        let buf = encode(0x0f0f_f0f0);
+        assert_eq!(peek_in_range(buf.as_ref(), 0..=0x0f0f_f0ef), Ok(false));
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            peek_in_range(buf.as_ref(), 0x0f0f_f0f1..=u64::MAX),
+            Ok(false)
         );
     }
     #[test]
-    fn enc_dec_max_u8() {
-        let val = u8::MAX.into();
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xbf, 0x03]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..1].copy_from_slice(&encoded_slice[1..]);
+    fn peek_in_range_err() {
+        assert_eq!(peek_in_range(&[], 0..=u64::MAX), Err(Error::Truncated));
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            peek_in_range(&[0xDD, 0x00, 0x00], 0..=u64::MAX),
+            Err(Error::RedundantEncode)
         );
     }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_sum_values {
+    use super::{encode, pack, sum_values, Error};
     #[test]
-    fn enc_dec_zero() {
-        let val = 0u64;
-        assert_eq!(encoded_len(val), 1);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0x00]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..0].copy_from_slice(&encoded_slice[1..]);
+    fn sum_values_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f];
+        let buf = pack(&values);
+        assert_eq!(sum_values(&buf), Ok(values.iter().sum()));
+        assert_eq!(sum_values(&[]), Ok(0));
+    }
+    #[test]
+    fn sum_values_wrapping() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(encode(u64::MAX).as_ref());
+        buf.extend_from_slice(encode(3).as_ref());
+        assert_eq!(sum_values(&buf), Ok(2));
+    }
+    #[test]
+    fn sum_values_err() {
+        assert_eq!(sum_values(&[0x01, 0x8F]), Err(Error::Truncated));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_pack {
+    use super::{pack, unpack, Error, Lcg};
+    #[test]
+    fn pack_unpack_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        assert_eq!(unpack(&pack(&values)), Ok(values.to_vec()));
+        assert_eq!(pack(&[]), Vec::<u8>::new());
+        assert_eq!(unpack(&[]), Ok(vec![]));
+    }
+    #[test]
+    fn pack_unpack_random() {
+        let mut rng = Lcg(0x0f0f_f0f0);
+        for n in 0..100 {
+            let values: Vec<u64> = (0..n).map(|_| rng.next_spread()).collect();
+            assert_eq!(unpack(&pack(&values)), Ok(values));
+        }
+    }
+    #[test]
+    fn unpack_err() {
+        assert_eq!(unpack(&[0x01, 0x8F]), Err(Error::Truncated));
+        assert_eq!(unpack(&[0xDD, 0x00, 0x00]), Err(Error::RedundantEncode));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_slice_into {
+    use super::{decode_slice_into, pack, Error};
+    #[test]
+    fn decode_slice_into_exact() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let buf = pack(&values);
+        let mut out = [0u64; 5];
+        assert_eq!(decode_slice_into(&buf, &mut out), Ok(5));
+        assert_eq!(out, values);
+    }
+    #[test]
+    fn decode_slice_into_too_small() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let buf = pack(&values);
+        let mut out = [0u64; 3];
+        assert_eq!(decode_slice_into(&buf, &mut out), Err(Error::BufferFull));
+        assert_eq!(out, [0, 127, 128]);
+        assert_eq!(decode_slice_into(&buf, &mut []), Err(Error::BufferFull));
+    }
+    #[test]
+    fn decode_slice_into_larger() {
+        let values = [0, 127, 128];
+        let buf = pack(&values);
+        let mut out = [9u64; 6];
+        assert_eq!(decode_slice_into(&buf, &mut out), Ok(3));
+        assert_eq!(out, [0, 127, 128, 9, 9, 9]);
+        assert_eq!(decode_slice_into(&[], &mut out), Ok(0));
+    }
+    #[test]
+    fn decode_slice_into_err() {
+        let mut out = [0u64; 4];
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_slice_into(&[0x01, 0x8F], &mut out),
+            Err(Error::Truncated)
         );
     }
+}
+
+#[cfg(test)]
+mod test_vu64_is_canonical {
+    use super::{encode, Vu64};
+    #[test]
+    fn is_canonical_true() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            assert!(encode(val).is_canonical());
+            let vu64 = unsafe { Vu64::from_bytes_unchecked(encode(val).as_ref()) };
+            assert_eq!(vu64, encode(val));
+            assert!(vu64.is_canonical());
+        }
+    }
+    #[test]
+    fn is_canonical_false() {
+        // redundant
+        let vu64 = unsafe { Vu64::from_bytes_unchecked(&[0xDD, 0x00, 0x00]) };
+        assert!(!vu64.is_canonical());
+        // truncated
+        let vu64 = unsafe { Vu64::from_bytes_unchecked(&[0xE0, 0x0f]) };
+        assert!(!vu64.is_canonical());
+        // trailing bytes
+        let vu64 = unsafe { Vu64::from_bytes_unchecked(&[0x01, 0x02]) };
+        assert!(!vu64.is_canonical());
+    }
+    #[test]
+    #[should_panic(expected = "empty bytes of Vu64")]
+    fn from_bytes_unchecked_empty() {
+        let _ = unsafe { Vu64::from_bytes_unchecked(&[]) };
+    }
+}
+
+#[cfg(test)]
+mod test_decode_padded9 {
+    use super::{decode_padded9, encode, Error, MAX_BYTES};
+    #[test]
+    fn decode_padded9_1() {
+        let values = [
+            0,
+            127,
+            128,
+            0x1F_FFFF,
+            0x0f0f_f0f0,
+            0x0f0f_f0f0_0f0f,
+            0xFF_FFFF_FFFF_FFFF,
+            u64::MAX,
+        ];
+        for &val in values.iter() {
+            let mut buf = [0xAAu8; MAX_BYTES];
+            let encoded = encode(val);
+            buf[..encoded.as_ref().len()].copy_from_slice(encoded.as_ref());
+            assert_eq!(decode_padded9(&buf), Ok(val));
+        }
+    }
+    #[test]
+    fn decode_padded9_err() {
+        let buf = [0xDD, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(decode_padded9(&buf), Err(Error::RedundantEncode));
+        let buf = [0xFF, 0, 0, 0, 0, 0, 0, 0x01, 0];
+        assert_eq!(decode_padded9(&buf), Err(Error::RedundantEncode));
+    }
+}
+
+#[cfg(test)]
+mod test_compression_ratio {
+    use super::{compression_ratio, MAX_LEN1, MAX_LEN8};
+    #[test]
+    fn compression_ratio_1() {
+        assert_eq!(compression_ratio(0), 0.125);
+        assert_eq!(compression_ratio(MAX_LEN1), 0.125);
+        assert_eq!(compression_ratio(MAX_LEN1 + 1), 0.25);
+        assert_eq!(compression_ratio(MAX_LEN8), 1.0);
+        assert_eq!(compression_ratio(u64::MAX), 1.125);
+    }
+}
+
+#[cfg(test)]
+mod test_decode_as_f64 {
+    use super::{decode_as_f64, encode, Error};
+    #[test]
+    fn decode_as_f64_exact() {
+        for &val in [0, 127, 0x0f0f_f0f0, (1 << 53) - 1, 1 << 53].iter() {
+            let buf = encode(val);
+            let (f, len) = decode_as_f64(buf.as_ref()).unwrap();
+            assert_eq!(f as u64, val);
+            assert_eq!(len, buf.as_ref().len());
+        }
+    }
+    #[test]
+    fn decode_as_f64_rounded() {
+        let f53 = (1u64 << 53) as f64;
+        // 2^53 + 1 is not representable, and rounded to 2^53
+        let (f, _) = decode_as_f64(encode((1 << 53) + 1).as_ref()).unwrap();
+        assert_eq!(f, f53);
+        // 2^53 + 2 is representable
+        let (f, _) = decode_as_f64(encode((1 << 53) + 2).as_ref()).unwrap();
+        assert_eq!(f, f53 + 2.0);
+        let (f, _) = decode_as_f64(encode(u64::MAX).as_ref()).unwrap();
+        assert_eq!(f, 18446744073709551616.0);
+    }
+    #[test]
+    fn decode_as_f64_err() {
+        assert_eq!(decode_as_f64(&[0x8F]), Err(Error::Truncated));
+    }
+}
+
+#[cfg(test)]
+mod test_decode_at_offset {
+    use super::{decode_at_offset, Error};
+    #[test]
+    fn decode_at_offset_1() {
+        let buf = [0x01, 0x8F, 0x3c, 0x7F];
+        assert_eq!(decode_at_offset(&buf, 0), Ok((1, 1)));
+        assert_eq!(decode_at_offset(&buf, 1), Ok((0x0f0f, 3)));
+        assert_eq!(decode_at_offset(&buf, 3), Ok((127, 4)));
+    }
+    #[test]
+    fn decode_at_offset_err() {
+        let buf = [0x01, 0x8F, 0x3c, 0x7F];
+        assert_eq!(decode_at_offset(&buf, 4), Err(Error::Truncated));
+        assert_eq!(decode_at_offset(&buf, 100), Err(Error::Truncated));
+        assert_eq!(decode_at_offset(&buf[..2], 1), Err(Error::Truncated));
+        // 0x3c is a value on its own
+        assert_eq!(decode_at_offset(&buf, 2), Ok((0x3c, 3)));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_time_series {
+    use super::{decode_time_series, encode, Error, TimeSeriesEncoder};
+    #[test]
+    fn time_series_in_order() {
+        let timestamps = [1_700_000_000_000, 1_700_000_001_000, 1_700_000_002_500];
+        let mut enc = TimeSeriesEncoder::new();
+        for &ts in timestamps.iter() {
+            enc.push(ts).unwrap();
+        }
+        let buf = enc.into_bytes();
+        assert_eq!(buf.len(), 6 + 2 + 2);
+        let mut out = Vec::new();
+        assert_eq!(decode_time_series(&buf, &mut out), Ok(3));
+        assert_eq!(out, timestamps);
+    }
+    #[test]
+    fn time_series_equal() {
+        let mut enc = TimeSeriesEncoder::new();
+        enc.push(100).unwrap();
+        enc.push(100).unwrap();
+        enc.push(100).unwrap();
+        assert_eq!(enc.as_bytes(), &[100, 0, 0]);
+        let mut out = Vec::new();
+        assert_eq!(decode_time_series(enc.as_bytes(), &mut out), Ok(3));
+        assert_eq!(out, vec![100, 100, 100]);
+    }
+    #[test]
+    fn time_series_out_of_order() {
+        let mut enc = TimeSeriesEncoder::new();
+        enc.push(100).unwrap();
+        enc.push(110).unwrap();
+        assert_eq!(enc.push(109), Err(Error::NotMonotonic));
+        // the rejected timestamp is not appended
+        enc.push(120).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(decode_time_series(enc.as_bytes(), &mut out), Ok(3));
+        assert_eq!(out, vec![100, 110, 120]);
+    }
+    #[test]
+    fn time_series_err_overflow() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(encode(u64::MAX).as_ref());
+        buf.extend_from_slice(encode(1).as_ref());
+        let mut out = Vec::new();
+        assert_eq!(decode_time_series(&buf, &mut out), Err(Error::NotMonotonic));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_slice_push {
+    use super::{decode_slice_push, pack, Error};
+    use std::collections::VecDeque;
     #[test]
-    fn enc_dec_max_len1() {
-        let val = MAX_LEN1;
-        assert_eq!(encoded_len(val), 1);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0x7F]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..0].copy_from_slice(&encoded_slice[1..]);
-        assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
-        );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0x80, 0x02]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..1].copy_from_slice(&encoded_slice[1..]);
-        assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
-        );
+    fn decode_slice_push_vec() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let mut out = vec![5];
+        assert_eq!(decode_slice_push(&pack(&values), &mut out), Ok(5));
+        assert_eq!(out, vec![5, 0, 127, 128, 0x0f0f_f0f0, u64::MAX]);
     }
     #[test]
-    fn enc_dec_max_len2() {
-        let val = MAX_LEN2;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xBF, 0xFF]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..1].copy_from_slice(&encoded_slice[1..]);
-        assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
-        );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 3);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xC0, 0x00, 0x02]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..2].copy_from_slice(&encoded_slice[1..]);
-        assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
-        );
+    fn decode_slice_push_vec_deque() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let mut out = VecDeque::new();
+        assert_eq!(decode_slice_push(&pack(&values), &mut out), Ok(5));
+        assert_eq!(out, VecDeque::from(values.to_vec()));
     }
     #[test]
-    fn enc_dec_max_len3() {
-        let val = MAX_LEN3;
-        assert_eq!(encoded_len(val), 3);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xDF, 0xFF, 0xFF]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..2].copy_from_slice(&encoded_slice[1..]);
+    fn decode_slice_push_err() {
+        let mut out = Vec::new();
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_slice_push(&[0x01, 0x8F], &mut out),
+            Err(Error::Truncated)
         );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 4);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xE0, 0x00, 0x00, 0x02]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..3].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(out, vec![1]);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_split_values {
+    use super::{decode, encode, pack, split_values, Error};
+    #[test]
+    fn split_values_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let buf = pack(&values);
+        let chunks = split_values(&buf).unwrap();
+        assert_eq!(chunks.len(), values.len());
+        for (chunk, &val) in chunks.iter().zip(values.iter()) {
+            assert_eq!(*chunk, encode(val).as_ref());
+            assert_eq!(decode(chunk), Ok(val));
+        }
+        assert_eq!(split_values(&[]), Ok(vec![]));
+    }
+    #[test]
+    fn split_values_err() {
+        assert_eq!(split_values(&[0x01, 0x8F]), Err(Error::Truncated));
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            split_values(&[0x01, 0xDD, 0x00, 0x00]),
+            Err(Error::RedundantEncode)
         );
     }
+}
+
+#[cfg(test)]
+mod test_fuzz_decode {
+    use super::{encode, fuzz_decode, Lcg};
     #[test]
-    fn enc_dec_max_len4() {
-        let val = MAX_LEN4;
-        assert_eq!(encoded_len(val), 4);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xEF, 0xFF, 0xFF, 0xFF]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..3].copy_from_slice(&encoded_slice[1..]);
+    fn fuzz_decode_random() {
+        let mut rng = Lcg(0x0f0f_f0f0);
+        for _ in 0..10_000 {
+            let len = (rng.next() >> 60) as usize;
+            let data: Vec<u8> = (0..len).map(|_| (rng.next() >> 56) as u8).collect();
+            fuzz_decode(&data);
+        }
+    }
+    #[test]
+    fn fuzz_decode_valid() {
+        fuzz_decode(&[]);
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            fuzz_decode(encode(val).as_ref());
+        }
+        fuzz_decode(&[0xDD, 0x00, 0x00]);
+    }
+}
+
+#[cfg(test)]
+mod test_decode_with_canonical_flag {
+    use super::{decode_with_canonical_flag, encode, Error};
+    #[test]
+    fn canonical_flag_true() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            let buf = encode(val);
+            assert_eq!(
+                decode_with_canonical_flag(buf.as_ref()),
+                Ok((val, true, buf.as_ref().len()))
+            );
+        }
+    }
+    #[test]
+    fn canonical_flag_false() {
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_with_canonical_flag(&[0xDD, 0x00, 0x00]),
+            Ok((0x1D, false, 3))
         );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 5);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xF0, 0x00, 0x00, 0x00, 0x02]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..4].copy_from_slice(&encoded_slice[1..]);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_with_canonical_flag(&[0xFF, 0x01, 0, 0, 0, 0, 0, 0, 0]),
+            Ok((1, false, 9))
         );
     }
     #[test]
-    fn enc_dec_max_len5() {
-        let val = MAX_LEN5;
-        assert_eq!(encoded_len(val), 5);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xF7, 0xFF, 0xFF, 0xFF, 0xFF]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..4].copy_from_slice(&encoded_slice[1..]);
-        assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
-        );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 6);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xF8, 0x00, 0x00, 0x00, 0x00, 0x02]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..5].copy_from_slice(&encoded_slice[1..]);
+    fn canonical_flag_err() {
+        assert_eq!(decode_with_canonical_flag(&[]), Err(Error::Truncated));
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_with_canonical_flag(&[0xDD, 0x00]),
+            Err(Error::Truncated)
         );
     }
+}
+
+#[cfg(test)]
+mod test_decode_unchecked {
+    use super::{decode, decode_unchecked, encode};
+    #[test]
+    fn decode_unchecked_1() {
+        let values = [
+            0,
+            127,
+            128,
+            0x1F_FFFF,
+            0x0f0f_f0f0,
+            0x0f0f_f0f0_0f0f,
+            0xFF_FFFF_FFFF_FFFF,
+            u64::MAX,
+        ];
+        for &val in values.iter() {
+            let buf = encode(val);
+            let r = unsafe { decode_unchecked(buf.as_ref()) };
+            assert_eq!(r, val);
+            assert_eq!(Ok(r), decode(buf.as_ref()));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_estimate_bytes {
+    use super::{encoded_len, estimate_bytes, pack, MAX_BYTES};
     #[test]
-    fn enc_dec_max_len6() {
-        let val = MAX_LEN6;
-        assert_eq!(encoded_len(val), 6);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xFB, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..5].copy_from_slice(&encoded_slice[1..]);
+    fn estimate_bytes_1() {
+        assert_eq!(estimate_bytes(&[0; MAX_BYTES]), 0);
+        assert_eq!(estimate_bytes(&[1; MAX_BYTES]), 45);
+    }
+    #[test]
+    fn estimate_bytes_matches_pack() {
+        let values = [0, 127, 128, 0x0f0f, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let mut histogram = [0; MAX_BYTES];
+        for &val in values.iter() {
+            histogram[encoded_len(val) as usize - 1] += 1;
+        }
+        assert_eq!(histogram, [2, 2, 0, 1, 0, 0, 1, 0, 1]);
+        assert_eq!(estimate_bytes(&histogram), pack(&values).len());
+    }
+}
+
+#[cfg(test)]
+mod test_decode_ref {
+    use super::{decode_ref, encode, Error};
+    #[test]
+    fn decode_ref_1() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            let buf = encode(val);
+            let mut out = 5;
+            assert_eq!(decode_ref(buf.as_ref(), &mut out), Ok(buf.as_ref().len()));
+            assert_eq!(out, val);
+        }
+    }
+    #[test]
+    fn decode_ref_err() {
+        let mut out = 5;
+        assert_eq!(decode_ref(&[], &mut out), Err(Error::Truncated));
+        assert_eq!(decode_ref(&[0x8F], &mut out), Err(Error::Truncated));
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_ref(&[0xDD, 0x00, 0x00], &mut out),
+            Err(Error::RedundantEncode)
         );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 7);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xFC, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..6].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(out, 5);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_len_iter {
+    use super::{encoded_len, len_iter, pack, Error};
+    #[test]
+    fn len_iter_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX, 5];
+        let buf = pack(&values);
+        let lens: Vec<u8> = len_iter(&buf).map(|r| r.unwrap()).collect();
+        let expected: Vec<u8> = values.iter().map(|&v| encoded_len(v)).collect();
+        assert_eq!(lens, expected);
+        assert_eq!(lens, vec![1, 1, 2, 4, 7, 9, 1]);
+        assert_eq!(len_iter(&[]).next(), None);
+    }
+    #[test]
+    fn len_iter_err() {
+        let mut iter = len_iter(&[0x01, 0x8F]);
+        assert_eq!(iter.next(), Some(Ok(1)));
+        assert_eq!(iter.next(), Some(Err(Error::Truncated)));
+        assert_eq!(iter.next(), None);
+    }
+}
+
+#[cfg(test)]
+mod test_vu64_u64_le_bytes {
+    use super::{encode, Vu64};
+    #[test]
+    fn u64_le_bytes_round_trip() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX].iter() {
+            let vu64 = encode(val);
+            let bytes = vu64.to_u64_le_bytes();
+            assert_eq!(bytes, val.to_le_bytes());
+            assert_eq!(Vu64::from_u64_le_bytes(bytes), vu64);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "paranoid"))]
+mod test_paranoid {
+    use super::{encode, paranoid_check, Vu64};
+    #[test]
+    fn paranoid_ok() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            paranoid_check(val, &encode(val));
+        }
+    }
+    #[test]
+    #[should_panic(expected = "do not decode back")]
+    fn paranoid_corrupted_bytes() {
+        let mut vu64 = encode(0x0f0f);
+        vu64.bytes[1] ^= 0x01;
+        paranoid_check(0x0f0f, &vu64);
+    }
+    #[test]
+    #[should_panic(expected = "encoded length")]
+    fn paranoid_corrupted_length() {
+        let vu64 = unsafe { Vu64::from_bytes_unchecked(&[0x80, 0x01]) };
+        paranoid_check(1, &vu64);
+    }
+}
+
+#[cfg(test)]
+mod test_decode_or {
+    use super::{decode_or, decode_or_else, encode, Error};
+    #[test]
+    fn decode_or_valid() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            assert_eq!(decode_or(encode(val).as_ref(), 5), val);
+            assert_eq!(decode_or_else(encode(val).as_ref(), |_| 5), val);
+        }
+    }
+    #[test]
+    fn decode_or_default() {
+        assert_eq!(decode_or(&[], 5), 5);
+        assert_eq!(decode_or(&[0x8F], 5), 5);
+        assert_eq!(decode_or(&[0xDD, 0x00, 0x00], 5), 5);
+        assert_eq!(decode_or_else(&[], |_| 5), 5);
+        let val = decode_or_else(&[0xDD, 0x00, 0x00], |err| {
+            assert_eq!(err, Error::RedundantEncode);
+            6
+        });
+        assert_eq!(val, 6);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_concat_validated {
+    use super::{concat_validated, pack, unpack, Error};
+    #[test]
+    fn concat_validated_1() {
+        let a = pack(&[0, 127, 128]);
+        let b = pack(&[0x0f0f_f0f0, u64::MAX]);
+        let mut out = vec![0x05];
+        assert_eq!(concat_validated(&a, &b, &mut out), Ok(5));
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            unpack(&out),
+            Ok(vec![5, 0, 127, 128, 0x0f0f_f0f0, u64::MAX])
         );
+        let mut out = Vec::new();
+        assert_eq!(concat_validated(&[], &[], &mut out), Ok(0));
+        assert!(out.is_empty());
     }
     #[test]
-    fn enc_dec_max_len7() {
-        let val = MAX_LEN7;
-        assert_eq!(encoded_len(val), 7);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xFD, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..6].copy_from_slice(&encoded_slice[1..]);
+    fn concat_validated_err() {
+        let a = pack(&[0, 0x0f0f_f0f0]);
+        let b = pack(&[127]);
+        let mut out = Vec::new();
+        // `a` ends in the middle of a value
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            concat_validated(&a[..a.len() - 1], &b, &mut out),
+            Err(Error::Truncated)
         );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 8);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
         assert_eq!(
-            encoded_slice,
-            &[0xFE, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02]
+            concat_validated(&a, &[0xDD, 0x00, 0x00], &mut out),
+            Err(Error::RedundantEncode)
         );
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..7].copy_from_slice(&encoded_slice[1..]);
+        assert!(out.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod test_prefix_ones {
+    use super::{decoded_len, prefix_ones};
+    #[test]
+    fn prefix_ones_1() {
+        #[rustfmt::skip]
+        let data = [
+            (0x00, 0), (0x7F, 0), (0x80, 1), (0xBF, 1), (0xC0, 2), (0xDD, 2),
+            (0xE0, 3), (0xF0, 4), (0xF8, 5), (0xFC, 6), (0xFE, 7), (0xFF, 8),
+        ];
+        for &(byte, ones) in data.iter() {
+            assert_eq!(prefix_ones(byte), ones, "byte: 0x{:02X}", byte);
+        }
+    }
+    #[test]
+    fn prefix_ones_decoded_len() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(decoded_len(byte), prefix_ones(byte) as u8 + 1);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_sparse {
+    use super::{decode_sparse, pack, Error};
+    #[test]
+    fn decode_sparse_1() {
+        let entries = [
+            Some(0),
+            None,
+            None,
+            Some(0x0f0f_f0f0),
+            Some(127),
+            None,
+            None,
+            None,
+            Some(u64::MAX),
+            None,
+        ];
+        let mut bitmap = vec![0u8; 2];
+        let mut present = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if let Some(val) = entry {
+                bitmap[i / 8] |= 1 << (i % 8);
+                present.push(*val);
+            }
+        }
+        assert_eq!(bitmap, vec![0b0001_1001, 0b01]);
+        let values = pack(&present);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_sparse(&bitmap, &values, entries.len()),
+            Ok(entries.to_vec())
         );
     }
     #[test]
-    fn enc_dec_max_len8() {
-        let val = MAX_LEN8;
-        assert_eq!(encoded_len(val), 8);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
+    fn decode_sparse_all_absent() {
+        assert_eq!(decode_sparse(&[0], &[], 8), Ok(vec![None; 8]));
+        assert_eq!(decode_sparse(&[], &[], 0), Ok(vec![]));
+    }
+    #[test]
+    fn decode_sparse_err() {
+        // bitmap too short
+        assert_eq!(decode_sparse(&[0xFF], &[], 9), Err(Error::Truncated));
+        // values run out
+        assert_eq!(decode_sparse(&[0b11], &[0x01], 2), Err(Error::Truncated));
+        // extra values
         assert_eq!(
-            encoded_slice,
-            &[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+            decode_sparse(&[0b01], &[0x01, 0x02], 2),
+            Err(Error::TrailingBytes)
         );
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..7].copy_from_slice(&encoded_slice[1..]);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_fingerprint {
+    use super::{fingerprint, pack, unpack};
+    #[test]
+    fn fingerprint_stable() {
+        // the known values of FNV-1a 64
+        assert_eq!(fingerprint(&[]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fingerprint(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fingerprint(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+    #[test]
+    fn fingerprint_repack() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let buf = pack(&values);
+        let repacked = pack(&unpack(&buf).unwrap());
+        assert_eq!(fingerprint(&buf), fingerprint(&repacked));
+        assert_ne!(fingerprint(&buf), fingerprint(&pack(&values[1..])));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_slice_monotonic {
+    use super::{decode_slice_monotonic, pack, Error};
+    #[test]
+    fn decode_slice_monotonic_sorted() {
+        let values = [0, 1, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let mut out = Vec::new();
+        assert_eq!(decode_slice_monotonic(&pack(&values), &mut out), Ok(6));
+        assert_eq!(out, values);
+    }
+    #[test]
+    fn decode_slice_monotonic_err() {
+        let mut out = Vec::new();
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_slice_monotonic(&pack(&[1, 5, 3, 7]), &mut out),
+            Err(Error::NotMonotonic)
         );
-        //
-        let val = val + 1;
-        assert_eq!(encoded_len(val), 9);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
+        assert_eq!(out, vec![1, 5]);
+        // equal values are not strictly increasing
+        let mut out = Vec::new();
         assert_eq!(
-            encoded_slice,
-            &[0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]
+            decode_slice_monotonic(&pack(&[1, 5, 5]), &mut out),
+            Err(Error::NotMonotonic)
         );
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..8].copy_from_slice(&encoded_slice[1..]);
+        let mut out = Vec::new();
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_slice_monotonic(&[0x01, 0x8F], &mut out),
+            Err(Error::Truncated)
         );
     }
+}
+
+#[cfg(test)]
+mod test_decode_with_prefix {
+    use super::{decode_with_prefix, encode, Error};
     #[test]
-    fn enc_dec_len_6() {
-        let val = 0x0300_0000_0000;
-        assert_eq!(encoded_len(val), 6);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xf8, 0x00, 0x00, 0x00, 0x00, 0xc0]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..5].copy_from_slice(&encoded_slice[1..]);
+    fn decode_with_prefix_1() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            let buf = encode(val);
+            let bytes = buf.as_ref();
+            assert_eq!(decode_with_prefix(bytes), Ok((val, bytes[0], bytes.len())));
+        }
+    }
+    #[test]
+    fn decode_with_prefix_err() {
+        assert_eq!(decode_with_prefix(&[]), Err(Error::Truncated));
+        assert_eq!(decode_with_prefix(&[0x8F]), Err(Error::Truncated));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_recanonicalize_in_place {
+    use super::{pack, recanonicalize_in_place, Error, ParsedBuffer};
+    #[test]
+    fn recanonicalize_redundant() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&[0xDD, 0x00, 0x00]); // 0x1D in 3 bytes
+        buf.extend_from_slice(&pack(&[0x0f0f]));
+        buf.extend_from_slice(&[0xFF, 0x01, 0, 0, 0, 0, 0, 0, 0]); // 1 in 9 bytes
+        buf.extend_from_slice(&[0xC0, 0x04, 0x00]); // 0x80 in 3 bytes
+        let old_len = buf.len();
+        let new_len = recanonicalize_in_place(&mut buf).unwrap();
+        assert!(new_len < old_len);
+        assert_eq!(buf.len(), new_len);
+        assert!(ParsedBuffer::new(&buf).is_ok());
+        assert_eq!(buf, pack(&[0x1D, 0x0f0f, 1, 0x80]));
+    }
+    #[test]
+    fn recanonicalize_canonical() {
+        let orig = pack(&[0, 127, 128, 0x0f0f_f0f0, u64::MAX]);
+        let mut buf = orig.clone();
+        assert_eq!(recanonicalize_in_place(&mut buf), Ok(orig.len()));
+        assert_eq!(buf, orig);
+    }
+    #[test]
+    fn recanonicalize_err() {
+        let orig = vec![0xDD, 0x00, 0x00, 0x8F];
+        let mut buf = orig.clone();
+        assert_eq!(recanonicalize_in_place(&mut buf), Err(Error::Truncated));
+        assert_eq!(buf, orig);
+    }
+}
+
+#[cfg(all(test, feature = "tables"))]
+mod test_decoded_len_tbl {
+    use super::{decoded_len, decoded_len_tbl};
+    #[test]
+    fn decoded_len_tbl_all() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(
+                decoded_len_tbl(byte),
+                decoded_len(byte),
+                "byte: 0x{:02X}",
+                byte
+            );
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_ratio {
+    use super::{decode_ratio, encode_ratio, Error};
+    #[test]
+    fn ratio_round_trip() {
+        let data = [(1, 3), (0, 1), (u64::MAX, 0x0f0f_f0f0), (22, 7)];
+        for &(num, den) in data.iter() {
+            let mut buf = Vec::new();
+            encode_ratio(num, den, &mut buf);
+            assert_eq!(decode_ratio(&buf), Ok((num, den, buf.len())));
+        }
+    }
+    #[test]
+    fn ratio_zero_denominator() {
+        let mut buf = Vec::new();
+        encode_ratio(5, 0, &mut buf);
+        assert_eq!(buf, vec![0x05, 0x00]);
+        assert_eq!(decode_ratio(&buf), Ok((5, 0, 2)));
+    }
+    #[test]
+    fn ratio_err() {
+        assert_eq!(decode_ratio(&[]), Err(Error::Truncated));
+        // the denominator is missing
+        assert_eq!(decode_ratio(&[0x05]), Err(Error::Truncated));
+        assert_eq!(decode_ratio(&[0x05, 0x8F]), Err(Error::Truncated));
+    }
+}
+
+#[cfg(test)]
+mod test_decode_with_len {
+    use super::{decode, decode_with_len, decoded_len, encode, Error};
+    #[test]
+    fn decode_with_len_1() {
+        let values = [
+            0,
+            127,
+            128,
+            0x1F_FFFF,
+            0x0f0f_f0f0,
+            0x0f0f_f0f0_0f0f,
+            0xFF_FFFF_FFFF_FFFF,
+            u64::MAX,
+        ];
+        for &val in values.iter() {
+            let buf = encode(val);
+            let bytes = buf.as_ref();
+            let (v, len) = decode_with_len(bytes).unwrap();
+            assert_eq!(v, val);
+            assert_eq!(len, bytes.len());
+            assert_eq!(len, decoded_len(bytes[0]) as usize);
+        }
+    }
+    #[test]
+    fn decode_with_len_err() {
+        let data: [&[u8]; 4] = [&[], &[0x8F], &[0xDD, 0x00, 0x00], &[0xE0, 0x0f]];
+        for &bytes in data.iter() {
+            assert_eq!(decode_with_len(bytes).map(|(v, _)| v), decode(bytes));
+        }
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_with_len(&[0xDD, 0x00, 0x00]),
+            Err(Error::RedundantEncode)
         );
     }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_advance {
+    use super::{decode_advance, pack, Error};
     #[test]
-    fn enc_dec_len_7() {
-        let val = 0x1_0000_0000_0000;
-        assert_eq!(encoded_len(val), 7);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(encoded_slice, &[0xfc, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80]);
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..6].copy_from_slice(&encoded_slice[1..]);
+    fn decode_advance_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX];
+        let buf = pack(&values);
+        let mut bytes = buf.as_slice();
+        for &val in values.iter() {
+            assert_eq!(decode_advance(&mut bytes), Ok(val));
+        }
+        assert!(bytes.is_empty());
+        assert_eq!(decode_advance(&mut bytes), Err(Error::Truncated));
+    }
+    #[test]
+    fn decode_advance_err_untouched() {
+        let buf = [0x01, 0xDD, 0x00, 0x00];
+        let mut bytes = &buf[..];
+        assert_eq!(decode_advance(&mut bytes), Ok(1));
+        assert_eq!(decode_advance(&mut bytes), Err(Error::RedundantEncode));
+        assert_eq!(bytes, &[0xDD, 0x00, 0x00]);
+        //
+        let buf = [0x8F];
+        let mut bytes = &buf[..];
+        assert_eq!(decode_advance(&mut bytes), Err(Error::Truncated));
+        assert_eq!(bytes, &[0x8F]);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_slice_collect_errors {
+    use super::{decode_slice_collect_errors, pack, Error};
+    #[test]
+    fn collect_errors_valid() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let buf = pack(&values);
+        let (vals, errors) = decode_slice_collect_errors(&buf);
+        assert_eq!(vals, values.to_vec());
+        assert!(errors.is_empty());
+        //
+        let (vals, errors) = decode_slice_collect_errors(&[]);
+        assert!(vals.is_empty());
+        assert!(errors.is_empty());
+    }
+    #[test]
+    fn collect_errors_redundant() {
+        #[rustfmt::skip]
+        let buf = [
+            0x01,                   // 1
+            0x81, 0x00,             // 1 in 2 bytes
+            0x8F, 0x3c,             // 0x0f0f
+            0xC0, 0x04, 0x00,       // 0x80 in 3 bytes
+            0xDD, 0x00, 0x00,       // 0x1d in 3 bytes
+            0x7F,                   // 127
+            0xE0, 0x02, 0x00, 0x00, // 0x200 in 4 bytes
+            0x00,                   // 0
+        ];
+        let (vals, errors) = decode_slice_collect_errors(&buf);
+        assert_eq!(vals, vec![1, 0x0f0f, 127, 0]);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            errors,
+            vec![
+                (1, Error::RedundantEncode),
+                (5, Error::RedundantEncode),
+                (8, Error::RedundantEncode),
+                (12, Error::RedundantEncode),
+            ]
         );
     }
     #[test]
-    fn enc_dec_len_8() {
-        let val = 0x00f0_0000_0000_0000;
-        assert_eq!(encoded_len(val), 8);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
+    fn collect_errors_truncated() {
+        // 1 in 2 bytes, 2, and truncated 9 bytes
+        let buf = [0x81, 0x00, 0x02, 0xFF, 0x01, 0x02];
+        let (vals, errors) = decode_slice_collect_errors(&buf);
+        assert_eq!(vals, vec![2]);
         assert_eq!(
-            encoded_slice,
-            &[0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0]
+            errors,
+            vec![(0, Error::RedundantEncode), (3, Error::Truncated)]
         );
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..7].copy_from_slice(&encoded_slice[1..]);
+    }
+}
+
+#[cfg(test)]
+mod test_encode_const {
+    use super::{encode, encode_const, encoded_len, encoded_len_const, Vu64};
+    #[test]
+    fn encode_const_boundary() {
+        for n in 0..64 {
+            let p = 1u64 << n;
+            for &val in [p - 1, p, p + 1].iter() {
+                assert_eq!(encode_const(val), encode(val), "val: {val:#x}");
+                assert_eq!(encoded_len_const(val), encoded_len(val), "val: {val:#x}");
+            }
+        }
+        for k in 1..=9u32 {
+            let max = if k >= 9 {
+                u64::MAX
+            } else {
+                (1u64 << (7 * k)) - 1
+            };
+            for &val in [max - 1, max, max.wrapping_add(1)].iter() {
+                assert_eq!(encode_const(val), encode(val), "val: {val:#x}");
+            }
+        }
+        assert_eq!(encode_const(0), encode(0));
+        assert_eq!(encode_const(u64::MAX), encode(u64::MAX));
+    }
+    #[test]
+    fn encode_const_in_const() {
+        const TBL: [Vu64; 3] = [
+            encode_const(0),
+            encode_const(0x0f0f),
+            encode_const(u64::MAX),
+        ];
+        assert_eq!(TBL[0].as_ref(), &[0x00]);
+        assert_eq!(TBL[1].as_ref(), &[0x8F, 0x3c]);
+        assert_eq!(TBL[2].as_ref(), &[0xFF; 9]);
+    }
+}
+
+#[cfg(test)]
+mod test_vu64_len {
+    use super::{encode, MAX_BYTES};
+    #[test]
+    fn len_1() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            let vu64 = encode(val);
+            assert_eq!(vu64.len(), vu64.as_ref().len());
+            assert!(!vu64.is_empty());
+        }
+        assert_eq!(encode(0).len(), 1);
+        assert_eq!(encode(u64::MAX).len(), MAX_BYTES);
+    }
+}
+
+#[cfg(test)]
+mod test_vu64_value {
+    use super::{encode, signed};
+    #[test]
+    fn value_1() {
+        for n in 0..64 {
+            let p = 1u64 << n;
+            for &val in [p - 1, p, p + 1].iter() {
+                assert_eq!(encode(val).value(), val);
+            }
+        }
+        assert_eq!(encode(u64::MAX).value(), u64::MAX);
+    }
+    #[test]
+    fn signed_value_1() {
+        for &val in [0, -1, 1, -64, 64, i64::MIN, i64::MAX].iter() {
+            assert_eq!(signed::encode(val).signed_value(), val);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_slice_map {
+    use super::{decode_slice_map, pack, Error};
+    #[test]
+    fn decode_slice_map_f64() {
+        let buf = pack(&[0, 150, 12345]);
+        let mut out = Vec::new();
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_slice_map(&buf, |v| v as f64 / 100.0, &mut out),
+            Ok(3)
         );
+        assert_eq!(out, vec![0.0, 1.5, 123.45]);
     }
     #[test]
-    fn enc_dec_len_9() {
-        let val = 0x0100_0000_0000_0000;
-        assert_eq!(encoded_len(val), 9);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
+    fn decode_slice_map_struct() {
+        #[derive(Debug, PartialEq)]
+        struct Id {
+            id: u64,
+            is_even: bool,
+        }
+        let buf = pack(&[1, 0x0f0f_f0f0]);
+        let mut out = Vec::new();
+        let r = decode_slice_map(
+            &buf,
+            |v| Id {
+                id: v,
+                is_even: v % 2 == 0,
+            },
+            &mut out,
+        );
+        assert_eq!(r, Ok(2));
         assert_eq!(
-            encoded_slice,
-            &[0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]
+            out,
+            vec![
+                Id {
+                    id: 1,
+                    is_even: false
+                },
+                Id {
+                    id: 0x0f0f_f0f0,
+                    is_even: true
+                },
+            ]
         );
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..8].copy_from_slice(&encoded_slice[1..]);
+    }
+    #[test]
+    fn decode_slice_map_err() {
+        let mut out = Vec::new();
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_slice_map(&[0x01, 0x8F], |v| v, &mut out),
+            Err(Error::Truncated)
         );
+        assert_eq!(out, vec![1]);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_max_encoded_len {
+    use super::{encode, max_encoded_len, pack, Error};
+    #[test]
+    fn max_encoded_len_1() {
+        let mut buf = pack(&[1, 0x0f0f_f0f0, 127, 0x0f0f, 0]);
+        assert_eq!(max_encoded_len(&buf), Ok(4));
+        buf.extend_from_slice(encode(u64::MAX).as_ref());
+        assert_eq!(max_encoded_len(&buf), Ok(9));
+        assert_eq!(max_encoded_len(&[0x00]), Ok(1));
+        assert_eq!(max_encoded_len(&[]), Ok(0));
+    }
+    #[test]
+    fn max_encoded_len_truncated() {
+        assert_eq!(max_encoded_len(&[0x01, 0x8F]), Err(Error::Truncated));
     }
 }
 
 #[cfg(test)]
-mod test_u64_2 {
-    use super::{decode, decode2, decode3, encode, encoded_len};
+mod test_vu64_ord {
+    use super::{encode, MAX_LEN1};
+    use std::collections::BTreeMap;
     #[test]
-    fn enc_dec_all() {
-        let mut val: u64 = 1;
-        for _i in 0..64 {
-            val = (val << 1) | 0x01;
-            let encoded = encode(val);
-            let encoded_slice = encoded.as_ref();
-            assert_eq!(decode(encoded_slice).unwrap(), val);
-            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-            let mut buf = [0u8; 8];
-            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
-            assert_eq!(
-                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-                val
-            );
+    fn ord_1() {
+        // 0x7F < 0x80 0x02
+        assert!(encode(MAX_LEN1) < encode(MAX_LEN1 + 1));
+        // 255 is 0xBF 0x03, 256 is 0x80 0x04: the byte order is the opposite
+        assert!(encode(255) < encode(256));
+        assert!(encode(255).as_ref() > encode(256).as_ref());
+        assert_eq!(encode(5).cmp(&encode(5)), std::cmp::Ordering::Equal);
+        //
+        let values = [0x0f0f_f0f0, 127, u64::MAX, 0, 128, 0x0f0f, 1];
+        let mut v: Vec<_> = values.iter().map(|&val| encode(val)).collect();
+        v.sort();
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        let v: Vec<u64> = v.iter().map(|a| a.value()).collect();
+        assert_eq!(v, sorted);
+    }
+    #[test]
+    fn ord_btree_map() {
+        let mut map = BTreeMap::new();
+        map.insert(encode(128), "b");
+        map.insert(encode(127), "a");
+        map.insert(encode(u64::MAX), "c");
+        let keys: Vec<u64> = map.keys().map(|a| a.value()).collect();
+        assert_eq!(keys, vec![127, 128, u64::MAX]);
+        assert_eq!(map.get(&encode(128)), Some(&"b"));
+    }
+}
+
+#[cfg(test)]
+mod test_vu64_hash {
+    use super::{encode, signed, Vu64};
+    use std::collections::HashSet;
+    use std::convert::TryFrom;
+    #[test]
+    fn hash_set_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let set: HashSet<_> = values.iter().map(|&val| encode(val)).collect();
+        assert_eq!(set.len(), values.len());
+        for &val in values.iter() {
+            assert!(set.contains(&encode(val)));
+            assert!(set.contains(&Vu64::try_from(encode(val).as_ref()).unwrap()));
         }
+        assert!(!set.contains(&encode(1)));
+        assert!(!set.contains(&encode(129)));
+        // the zigzag encoding of -1 is 1
+        assert!(!set.contains(&signed::encode(-1)));
+        assert!(set.contains(&signed::encode(-64)) == set.contains(&encode(127)));
     }
+}
+
+#[cfg(test)]
+mod test_vu64_set {
+    use super::{decode, encode, signed};
     #[test]
-    fn enc_dec_all_1byte() {
-        let f = |val| {
-            assert_eq!(encoded_len(val), 1);
-            let encoded = encode(val);
-            let encoded_slice = encoded.as_ref();
-            assert_eq!(decode(encoded_slice).unwrap(), val);
-            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-            let mut buf = [0u8; 8];
-            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
-            assert_eq!(
-                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-                val
-            );
-        };
-        if cfg!(miri) {
-            for val in [0, 127].into_iter() {
-                f(val);
-            }
-        } else {
-            for val in 0..128 {
-                f(val);
+    fn set_1() {
+        let mut vu64 = encode(u64::MAX);
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX, 1].iter() {
+            vu64.set(val);
+            assert_eq!(vu64, encode(val));
+            assert_eq!(decode(vu64.as_ref()), Ok(val));
+        }
+    }
+    #[test]
+    fn set_signed_1() {
+        let mut vu64 = encode(u64::MAX);
+        for &val in [0, -1, 1, -64, 64, i64::MIN, i64::MAX, -2].iter() {
+            vu64.set_signed(val);
+            assert_eq!(vu64, signed::encode(val));
+            assert_eq!(signed::decode(vu64.as_ref()), Ok(val));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_encode_to_slice {
+    use super::{encode, encode_to_slice, Error, MAX_BYTES};
+    #[test]
+    fn encode_to_slice_1() {
+        for n in 0..64 {
+            let p = 1u64 << n;
+            for &val in [p - 1, p, p + 1, u64::MAX].iter() {
+                let mut buf = [0xAAu8; MAX_BYTES + 1];
+                let len = encode_to_slice(val, &mut buf).unwrap();
+                assert_eq!(&buf[..len], encode(val).as_ref(), "val: {val:#x}");
+                assert!(buf[len..].iter().all(|&b| b == 0xAA));
+                // exactly enough
+                let mut buf = vec![0u8; len];
+                assert_eq!(encode_to_slice(val, &mut buf), Ok(len));
+                assert_eq!(buf.as_slice(), encode(val).as_ref());
             }
-        };
+        }
     }
     #[test]
-    fn enc_dec_all_2byte() {
-        let f = |val| {
-            assert_eq!(encoded_len(val), 2);
-            let encoded = encode(val);
-            let encoded_slice = encoded.as_ref();
-            assert_eq!(decode(encoded_slice).unwrap(), val);
-            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-            let mut buf = [0u8; 8];
-            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+    fn encode_to_slice_err() {
+        let mut buf = [0u8; 1];
+        assert_eq!(encode_to_slice(128, &mut buf), Err(Error::BufferFull));
+        assert_eq!(buf, [0u8; 1]);
+        assert_eq!(encode_to_slice(0, &mut []), Err(Error::BufferFull));
+        let mut buf = [0u8; MAX_BYTES - 1];
+        assert_eq!(encode_to_slice(u64::MAX, &mut buf), Err(Error::BufferFull));
+    }
+}
+
+#[cfg(test)]
+mod test_decode_progress {
+    use super::{decode_progress, encode, DecodeProgress, Error};
+    #[test]
+    fn decode_progress_complete() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            let enc = encode(val);
+            let mut buf = enc.as_ref().to_vec();
             assert_eq!(
-                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-                val
+                decode_progress(&buf),
+                DecodeProgress::Complete(val, buf.len())
             );
-        };
-        if cfg!(miri) {
-            for val in [128, 16383].into_iter() {
-                f(val);
-            }
-        } else {
-            for val in 128..16384 {
-                f(val);
-            }
-        };
+            buf.push(0x01);
+            assert_eq!(
+                decode_progress(&buf),
+                DecodeProgress::Complete(val, buf.len() - 1)
+            );
+        }
     }
     #[test]
-    fn enc_dec_all_3byte() {
-        let f = |val| {
-            assert_eq!(encoded_len(val), 3);
-            let encoded = encode(val);
-            let encoded_slice = encoded.as_ref();
-            assert_eq!(decode(encoded_slice).unwrap(), val);
-            assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-            let mut buf = [0u8; 8];
-            buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+    fn decode_progress_need_more() {
+        assert_eq!(decode_progress(&[]), DecodeProgress::NeedMore(1));
+        let enc = encode(u64::MAX);
+        for i in 1..enc.as_ref().len() {
             assert_eq!(
-                decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-                val
+                decode_progress(&enc.as_ref()[..i]),
+                DecodeProgress::NeedMore(9 - i)
             );
-        };
-        if cfg!(miri) {
-            for val in [16384, 2097151].into_iter() {
-                f(val);
-            }
-        } else {
-            for val in 16384..2097152 {
-                f(val);
-            }
-        };
+        }
+        assert_eq!(decode_progress(&[0x8F]), DecodeProgress::NeedMore(1));
     }
     #[test]
-    fn enc_dec_sp1() {
-        let val = 0x06_6C80;
-        assert_eq!(encoded_len(val), 3);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+    fn decode_progress_invalid() {
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            decode_progress(&[0xDD, 0x00, 0x00]),
+            DecodeProgress::Invalid(Error::RedundantEncode)
+        );
+        assert_eq!(
+            decode_progress(&[0x81, 0x00]),
+            DecodeProgress::Invalid(Error::RedundantEncode)
         );
     }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_footer {
+    use super::{decode_with_footer, encode_with_footer, Error};
     #[test]
-    fn enc_dec_sp2() {
-        let val = 136;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+    fn footer_round_trip() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX];
+        let mut buf = Vec::new();
+        encode_with_footer(&values, &mut buf);
+        assert_eq!(&buf[buf.len() - 8..], &5u64.to_le_bytes());
+        assert_eq!(decode_with_footer(&buf), Ok(values.to_vec()));
+        //
+        let mut buf = Vec::new();
+        encode_with_footer(&[], &mut buf);
+        assert_eq!(buf, vec![0u8; 8]);
+        assert_eq!(decode_with_footer(&buf), Ok(vec![]));
+    }
+    #[test]
+    fn footer_mismatch() {
+        let mut buf = Vec::new();
+        encode_with_footer(&[1, 2, 3], &mut buf);
+        // the count is greater than the values
+        let mut bad = buf.clone();
+        bad[3] = 4;
+        assert_eq!(decode_with_footer(&bad), Err(Error::Truncated));
+        // the count is less than the values
+        let mut bad = buf.clone();
+        bad[3] = 2;
+        assert_eq!(decode_with_footer(&bad), Err(Error::TrailingBytes));
+        // a huge count
+        let mut bad = buf.clone();
+        bad[10] = 0xFF;
+        assert_eq!(decode_with_footer(&bad), Err(Error::Truncated));
+        // no footer
+        assert_eq!(decode_with_footer(&buf[..7]), Err(Error::Truncated));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_decode_iter {
+    use super::{decode_iter, pack, Error};
+    #[test]
+    fn decode_iter_1() {
+        let values = [0, 127, 128, 0x0f0f_f0f0, u64::MAX, 1];
+        let buf = pack(&values);
+        let v: Result<Vec<u64>, Error> = decode_iter(&buf).collect();
+        assert_eq!(v, Ok(values.to_vec()));
+        assert_eq!(decode_iter(&[]).next(), None);
+    }
+    #[test]
+    fn decode_iter_err() {
+        let mut it = decode_iter(&[0x01, 0xFF, 0x00]);
+        assert_eq!(it.next(), Some(Ok(1)));
+        assert_eq!(it.next(), Some(Err(Error::Truncated)));
+        assert_eq!(it.next(), None);
+        //
+        let mut it = decode_iter(&[0x81, 0x00, 0x01]);
+        assert_eq!(it.next(), Some(Err(Error::RedundantEncode)));
+        assert_eq!(it.next(), None);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_encode_all {
+    use super::{decode_iter, encode_all, pack, Error};
+    #[test]
+    fn encode_all_round_trip() {
+        let values = vec![0, 127, 128, 0x0f0f_f0f0, u64::MAX, 1];
+        let buf = encode_all(values.iter().copied());
+        assert_eq!(buf, pack(&values));
+        let v: Result<Vec<u64>, Error> = decode_iter(&buf).collect();
+        assert_eq!(v, Ok(values.clone()));
+        //
+        let buf = encode_all(values.clone());
+        assert_eq!(buf, pack(&values));
+        assert!(encode_all(core::iter::empty()).is_empty());
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_interleave {
+    use super::{deinterleave, interleave, pack, Error};
+    #[test]
+    fn interleave_round_trip() {
+        let col_a = [0, 127, 128, u64::MAX];
+        let col_b = [0x0f0f_f0f0, 1, 0x0f0f, 2];
+        let (a, b) = (pack(&col_a), pack(&col_b));
+        let mut rows = Vec::new();
+        interleave(&a, &b, &mut rows).unwrap();
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            rows,
+            pack(&[0, 0x0f0f_f0f0, 127, 1, 128, 0x0f0f, u64::MAX, 2])
         );
+        let (mut a2, mut b2) = (Vec::new(), Vec::new());
+        deinterleave(&rows, &mut a2, &mut b2).unwrap();
+        assert_eq!(a2, a);
+        assert_eq!(b2, b);
         //
-        let val = 288;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        let mut rows = Vec::new();
+        interleave(&[], &[], &mut rows).unwrap();
+        assert!(rows.is_empty());
+    }
+    #[test]
+    fn interleave_err() {
+        let mut out = vec![0x7F];
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            interleave(&pack(&[1, 2]), &pack(&[3]), &mut out),
+            Err(Error::CountMismatch)
         );
-        //
-        let val = 569;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            interleave(&pack(&[1]), &pack(&[3, 4]), &mut out),
+            Err(Error::CountMismatch)
         );
-        //
-        let val = 1144;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            interleave(&[0x01], &[0x8F], &mut out),
+            Err(Error::Truncated)
         );
-        //
-        let val = 2296;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(out, vec![0x7F]);
+    }
+    #[test]
+    fn deinterleave_err() {
+        let (mut a, mut b) = (vec![0x7F], Vec::new());
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            deinterleave(&pack(&[1, 2, 3]), &mut a, &mut b),
+            Err(Error::CountMismatch)
         );
-        //
-        let val = 4601;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            deinterleave(&[0x01, 0x8F], &mut a, &mut b),
+            Err(Error::Truncated)
         );
-        //
-        let val = 9209;
-        assert_eq!(encoded_len(val), 2);
-        let encoded = encode(val);
-        let encoded_slice = encoded.as_ref();
-        assert_eq!(decode(encoded_slice).unwrap(), val);
-        assert_eq!(decode2(encoded_slice[0], &encoded_slice[1..]).unwrap(), val);
-        let mut buf = [0u8; 8];
-        buf[..encoded_slice.len() - 1].copy_from_slice(&encoded_slice[1..]);
+        assert_eq!(a, vec![0x7F]);
+        assert!(b.is_empty());
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_versioned {
+    use super::{decode_versioned, encode_versioned, Error};
+    #[test]
+    fn versioned_match() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            let mut buf = Vec::new();
+            encode_versioned(7, val, &mut buf);
+            assert_eq!(buf[0], 7);
+            assert_eq!(decode_versioned(&buf, 7), Ok((val, buf.len())));
+        }
+    }
+    #[test]
+    fn versioned_mismatch() {
+        let mut buf = Vec::new();
+        encode_versioned(1, 0x0f0f, &mut buf);
+        assert_eq!(decode_versioned(&buf, 0), Err(Error::Unexpected));
+        assert_eq!(decode_versioned(&buf, 2), Err(Error::Unexpected));
+        assert_eq!(decode_versioned(&[], 1), Err(Error::Truncated));
+        assert_eq!(decode_versioned(&buf[..2], 1), Err(Error::Truncated));
+    }
+}
+
+#[cfg(test)]
+mod test_from_be_fixed {
+    use super::{encode, from_be_fixed, Error};
+    #[test]
+    fn from_be_fixed_widths() {
+        assert_eq!(from_be_fixed(&[0x7F]), Ok(encode(0x7F)));
+        assert_eq!(from_be_fixed(&[0xFF]), Ok(encode(0xFF)));
+        assert_eq!(from_be_fixed(&[0x0f, 0xf0]), Ok(encode(0x0ff0)));
         assert_eq!(
-            decode3(encoded_slice[0], u64::from_le_bytes(buf)).unwrap(),
-            val
+            from_be_fixed(&[0x0f, 0x0f, 0xf0, 0xf0]),
+            Ok(encode(0x0f0f_f0f0))
         );
+        assert_eq!(from_be_fixed(&u64::MAX.to_be_bytes()), Ok(encode(u64::MAX)));
     }
     #[test]
-    fn decode_err_truncated() {
-        let slice = [0xF0].as_ref();
-        assert!(decode(slice).is_err());
-        assert!(decode2(slice[0], &slice[1..]).is_err());
-        let slice = [0xF8, 0x0f, 0xff].as_ref();
-        assert!(decode(slice).is_err());
-        assert!(decode2(slice[0], &slice[1..]).is_err());
-        //assert!(decode3(slice[0], 0).is_err());
+    fn from_be_fixed_leading_zeros() {
+        assert_eq!(from_be_fixed(&[0x00]), Ok(encode(0)));
+        assert_eq!(from_be_fixed(&[0x00, 0x01]), Ok(encode(1)));
+        assert_eq!(from_be_fixed(&[0, 0, 0x0f, 0x0f]), Ok(encode(0x0f0f)));
+        let r = from_be_fixed(&[0, 0, 0, 0, 0, 0, 0, 0x80]).unwrap();
+        assert_eq!(r.as_ref(), &[0x80, 0x02]);
     }
     #[test]
-    fn decode_err_leading_ones() {
-        let slice = [0xF8, 0x00, 0x00, 0x00].as_ref();
-        assert!(decode(slice).is_err());
-        assert!(decode2(slice[0], &slice[1..]).is_err());
-        //assert!(decode3(slice[0], 0).is_err());
+    fn from_be_fixed_err_width() {
+        assert_eq!(from_be_fixed(&[]), Err(Error::InvalidWidth));
+        assert_eq!(from_be_fixed(&[0; 3]), Err(Error::InvalidWidth));
+        assert_eq!(from_be_fixed(&[0; 5]), Err(Error::InvalidWidth));
+        assert_eq!(from_be_fixed(&[0; 9]), Err(Error::InvalidWidth));
     }
 }
 
 #[cfg(test)]
-mod test_u64_3 {
-    use super::{check_result_with_length, decode, encode, Error, Vu64};
+mod test_needed_bytes {
+    use super::{decoded_len, encode, needed_bytes, MAX_BYTES};
     #[test]
-    fn vu64_debug_format_1() {
-        let vu64 = encode(123456789);
-        assert_eq!(format!("{vu64:#?}"), "V64(123456789)");
+    fn needed_bytes_all() {
+        for byte in 0..=u8::MAX {
+            let n = needed_bytes(byte);
+            assert_eq!(n, decoded_len(byte) as usize);
+            assert!((1..=MAX_BYTES).contains(&n), "byte: {byte:#04x}");
+        }
+        assert_eq!(needed_bytes(0xFF), MAX_BYTES);
     }
     #[test]
-    fn try_from_1() {
-        let v = vec![0xE0u8, 0x0fu8, 0xffu8, 0xf0u8];
-        let r = Vu64::try_from(v.as_slice());
-        assert!(r.is_ok());
-        assert_eq!(format!("{:#?}", r.unwrap()), "V64(252702960)");
+    fn needed_bytes_encoded() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, u64::MAX].iter() {
+            let enc = encode(val);
+            assert_eq!(needed_bytes(enc.as_ref()[0]), enc.as_ref().len());
+        }
     }
+}
+
+#[cfg(test)]
+mod test_delta_encoded_len_slice {
+    use super::{delta_encoded_len_slice, encoded_len_slice};
     #[test]
-    fn try_into_1() {
-        let v = vec![0xE0u8, 0x0fu8, 0xffu8, 0xf0u8];
-        let r: Result<Vu64, Error> = v.as_slice().try_into();
-        assert!(r.is_ok());
-        assert_eq!(format!("{:#?}", r.unwrap()), "V64(252702960)");
+    fn delta_vs_raw_clustered() {
+        let values: Vec<u64> = (0..100).map(|i| 0x0f0f_f0f0_0f0f + 3 * i).collect();
+        let raw = encoded_len_slice(&values);
+        let delta = delta_encoded_len_slice(&values).unwrap();
+        assert_eq!(raw, 100 * 7);
+        assert_eq!(delta, 7 + 99);
+        assert!(delta < raw);
     }
     #[test]
-    fn decode_empty_1() {
-        let v: Vec<u8> = Vec::new();
-        let r = decode(v.as_slice());
-        assert!(r.is_err());
-        assert_eq!(r, Err(Error::Truncated));
+    fn delta_vs_raw_sparse() {
+        let values = [1, u64::MAX];
+        assert_eq!(encoded_len_slice(&values), 1 + 9);
+        assert_eq!(delta_encoded_len_slice(&values), Some(1 + 9));
     }
     #[test]
-    fn check_result_with_length_1() {
-        let r = check_result_with_length(2, 123456789);
-        assert!(r.is_ok());
-        let r = check_result_with_length(9, 123456789);
-        assert!(r.is_err());
-        assert_eq!(r, Err(Error::LeadingOnes));
+    fn delta_encoded_len_slice_edge() {
+        assert_eq!(delta_encoded_len_slice(&[]), Some(0));
+        assert_eq!(encoded_len_slice(&[]), 0);
+        assert_eq!(delta_encoded_len_slice(&[5, 5, 5]), Some(3));
+        assert_eq!(delta_encoded_len_slice(&[1, 5, 3]), None);
     }
+    #[cfg(feature = "alloc")]
     #[test]
-    fn error_format_1() {
-        let v: Vec<u8> = Vec::new();
-        let r = decode(v.as_slice());
-        assert!(r.is_err());
-        assert_eq!(r, Err(Error::Truncated));
-        if let Err(err) = r {
-            assert_eq!(format!("{err}"), "truncated vu64 value");
+    fn delta_encoded_len_slice_matches_encoder() {
+        let values = [1_700_000_000, 1_700_000_010, 1_700_000_010, 1_700_100_000];
+        let mut enc = super::TimeSeriesEncoder::new();
+        for &val in values.iter() {
+            enc.push(val).unwrap();
         }
-        let r = check_result_with_length(9, 123456789);
-        assert!(r.is_err());
-        assert_eq!(r, Err(Error::LeadingOnes));
-        if let Err(err) = r {
-            assert_eq!(format!("{err}"), "leading ones in vu64 value");
+        assert_eq!(delta_encoded_len_slice(&values), Some(enc.as_bytes().len()));
+        assert_eq!(encoded_len_slice(&values), super::pack(&values).len());
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_dump {
+    use super::{dump, pack};
+    #[test]
+    fn dump_known_buffer() {
+        let buf = pack(&[0, 127, 128, 0x0f0f_f0f0]);
+        assert_eq!(
+            dump(&buf),
+            concat!(
+                "0: 0 (1 bytes) [0x00]\n",
+                "1: 127 (1 bytes) [0x7f]\n",
+                "2: 128 (2 bytes) [0x80, 0x02]\n",
+                "3: 252702960 (4 bytes) [0xe0, 0x0f, 0xff, 0xf0]\n",
+            )
+        );
+        assert_eq!(dump(&[]), "");
+    }
+    #[test]
+    fn dump_err_tail() {
+        assert_eq!(
+            dump(&[0x01, 0xDD, 0x00, 0x00, 0x01]),
+            concat!(
+                "0: 1 (1 bytes) [0x01]\n",
+                "1: error: redundant encoded vu64 value [0xdd, 0x00, 0x00, 0x01]\n",
+            )
+        );
+        assert_eq!(
+            dump(&[0xFF, 0x01]),
+            "0: error: truncated vu64 value [0xff, 0x01]\n"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_encode_into {
+    use super::{encode, encode_into};
+    #[test]
+    fn encode_into_matches_encode() {
+        for n in 0..64 {
+            let p = 1u64 << n;
+            for &val in [p - 1, p, p + 1, u64::MAX].iter() {
+                let mut out = Vec::new();
+                encode_into(val, |b| out.push(b));
+                assert_eq!(out.as_slice(), encode(val).as_ref(), "val: {val:#x}");
+            }
         }
     }
+    #[test]
+    fn encode_into_appends() {
+        let mut out = vec![0xAA];
+        encode_into(0, |b| out.push(b));
+        encode_into(0x0f0f, |b| out.push(b));
+        assert_eq!(out, vec![0xAA, 0x00, 0x8F, 0x3c]);
+    }
 }
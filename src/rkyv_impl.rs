@@ -0,0 +1,131 @@
+/*!
+Supports of `rkyv`.
+
+The archived form of `Vu64` is the `Vu64` itself, so an archive can be
+accessed without any conversion. On access with validation, the `length`
+and the bytes are checked to be a canonical `vu64`, and the unused bytes
+after it to be zero, as `encode()` makes them.
+
+The `rkyv` feature needs rustc 1.81 or later, of `rkyv` 0.8.
+
+# Examples
+
+```
+use vu64::{encode, Vu64};
+let val = encode(0x0f0f_f0f0);
+let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&val).unwrap();
+let archived = rkyv::access::<Vu64, rkyv::rancor::Error>(&bytes).unwrap();
+assert_eq!(archived, &val);
+```
+*/
+use super::{decode, decoded_len, Vu64, MAX_BYTES};
+use rkyv::bytecheck::CheckBytes;
+use rkyv::rancor::{Fallible, Source};
+use rkyv::traits::NoUndef;
+use rkyv::{Archive, Deserialize, Place, Portable, Serialize};
+
+// SAFETY: `Vu64` is `#[repr(C)]` and consists of only `u8` fields,
+// so it has no padding and no alignment requirement.
+unsafe impl NoUndef for Vu64 {}
+
+// SAFETY: `Vu64` is `#[repr(C)]` and consists of only `u8` fields,
+// so it has the same layout on every platform.
+unsafe impl Portable for Vu64 {}
+
+impl Archive for Vu64 {
+    type Archived = Vu64;
+    type Resolver = ();
+
+    #[inline]
+    fn resolve(&self, _resolver: Self::Resolver, out: Place<Self::Archived>) {
+        out.write(*self);
+    }
+}
+
+impl<S: Fallible + ?Sized> Serialize<S> for Vu64 {
+    #[inline]
+    fn serialize(&self, _serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        Ok(())
+    }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<Vu64, D> for Vu64 {
+    #[inline]
+    fn deserialize(&self, _deserializer: &mut D) -> Result<Vu64, D::Error> {
+        Ok(*self)
+    }
+}
+
+// SAFETY: `check_bytes` returns `Ok` only when the `length` is in `1..=MAX_BYTES`,
+// the bytes are a canonical `vu64` of that length, and the rest bytes are zero.
+unsafe impl<C> CheckBytes<C> for Vu64
+where
+    C: Fallible + ?Sized,
+    C::Error: Source,
+{
+    unsafe fn check_bytes(value: *const Self, _context: &mut C) -> Result<(), C::Error> {
+        // SAFETY: every bit pattern is a valid `u8`, so the caller's
+        // guarantee of enough initialized bytes is sufficient.
+        let vu64 = unsafe { &*value };
+        let length = vu64.length as usize;
+        if length == 0 || length > MAX_BYTES || decoded_len(vu64.bytes[0]) as usize != length {
+            return Err(C::Error::new(crate::Error::Truncated));
+        }
+        // the rest bytes are compared by `PartialEq`, so they must be zero
+        if vu64.bytes[length..].iter().any(|&b| b != 0) {
+            return Err(C::Error::new(crate::Error::TrailingBytes));
+        }
+        decode(vu64.as_ref()).map(|_| ()).map_err(C::Error::new)
+    }
+}
+
+#[cfg(test)]
+mod test_rkyv {
+    use super::super::{encode, Vu64};
+    use rkyv::rancor::Error;
+    #[test]
+    fn round_trip_1() {
+        for val in [0, 1, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX] {
+            let vu64 = encode(val);
+            let bytes = rkyv::to_bytes::<Error>(&vu64).unwrap();
+            let archived = rkyv::access::<Vu64, Error>(&bytes).unwrap();
+            assert_eq!(archived, &vu64);
+            assert_eq!(archived.as_ref(), vu64.as_ref());
+            let deserialized = rkyv::deserialize::<Vu64, Error>(archived).unwrap();
+            assert_eq!(deserialized, vu64);
+        }
+    }
+    #[test]
+    fn access_err_length() {
+        let bytes = rkyv::to_bytes::<Error>(&encode(0x0f0f)).unwrap();
+        let mut bytes = bytes.to_vec();
+        // the `length` field comes first
+        bytes[0] = 0;
+        assert!(rkyv::access::<Vu64, Error>(&bytes).is_err());
+        bytes[0] = 10;
+        assert!(rkyv::access::<Vu64, Error>(&bytes).is_err());
+        bytes[0] = 3;
+        assert!(rkyv::access::<Vu64, Error>(&bytes).is_err());
+    }
+    #[test]
+    fn access_err_redundant() {
+        let bytes = rkyv::to_bytes::<Error>(&encode(0x0f0f)).unwrap();
+        let mut bytes = bytes.to_vec();
+        // 0x0f0f => [0x8F, 0x3c], change to the redundant [0x80, 0x00]
+        bytes[1] = 0x80;
+        bytes[2] = 0x00;
+        assert!(rkyv::access::<Vu64, Error>(&bytes).is_err());
+    }
+    #[test]
+    fn access_err_trailing_bytes() {
+        let bytes = rkyv::to_bytes::<Error>(&encode(0x0f0f)).unwrap();
+        let mut bytes = bytes.to_vec();
+        // 0x0f0f => [0x8F, 0x3c], and the rest bytes are zero
+        assert!(rkyv::access::<Vu64, Error>(&bytes).is_ok());
+        bytes[3] = 0x01;
+        assert!(rkyv::access::<Vu64, Error>(&bytes).is_err());
+        bytes[3] = 0x00;
+        bytes[super::MAX_BYTES] = 0x01;
+        assert!(rkyv::access::<Vu64, Error>(&bytes).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+/*!
+Supports of `serde`.
+
+For a human-readable format, e.g. JSON, `Vu64` is serialized as the decoded
+`u64` integer. For a binary format, it is serialized as the encoded bytes.
+On deserialization, the truncated and the redundant encodings are rejected
+with a serde error.
+
+# Examples
+
+```
+use vu64::encode;
+let val = encode(0x0f0f);
+let text = serde_json::to_string(&val).unwrap();
+assert_eq!(text, "3855");
+assert_eq!(serde_json::from_str::<vu64::Vu64>(&text).unwrap(), val);
+```
+*/
+use super::{decode_with_len, encode, Error, Vu64};
+use core::fmt;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+impl Serialize for Vu64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_u64(self.value())
+        } else {
+            serializer.serialize_bytes(self.as_ref())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Vu64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_u64(Vu64Visitor)
+        } else {
+            deserializer.deserialize_bytes(Vu64Visitor)
+        }
+    }
+}
+
+struct Vu64Visitor;
+
+impl<'de> Visitor<'de> for Vu64Visitor {
+    type Value = Vu64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an u64 integer or the vu64-encoded bytes")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Vu64, E> {
+        Ok(encode(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vu64, E> {
+        let (val, len) = decode_with_len(v).map_err(E::custom)?;
+        if len != v.len() {
+            return Err(E::custom(Error::TrailingBytes));
+        }
+        Ok(encode(val))
+    }
+}
+
+#[cfg(test)]
+mod test_serde {
+    use super::super::{encode, Vu64};
+    #[test]
+    fn json_round_trip() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX].iter() {
+            let vu64 = encode(val);
+            let text = serde_json::to_string(&vu64).unwrap();
+            assert_eq!(text, val.to_string());
+            assert_eq!(serde_json::from_str::<Vu64>(&text).unwrap(), vu64);
+        }
+        assert!(serde_json::from_str::<Vu64>("-1").is_err());
+        assert!(serde_json::from_str::<Vu64>("\"1\"").is_err());
+    }
+    #[test]
+    fn bincode_round_trip() {
+        for &val in [0, 127, 128, 0x0f0f_f0f0, 0x0f0f_f0f0_0f0f, u64::MAX].iter() {
+            let vu64 = encode(val);
+            let bytes = bincode::serialize(&vu64).unwrap();
+            // the 8 bytes length prefix, and the encoded bytes
+            assert_eq!(&bytes[8..], vu64.as_ref());
+            assert_eq!(bincode::deserialize::<Vu64>(&bytes).unwrap(), vu64);
+        }
+    }
+    #[test]
+    fn bincode_err() {
+        // truncated
+        let bytes = bincode::serialize(&[0x8Fu8][..]).unwrap();
+        let err = bincode::deserialize::<Vu64>(&bytes).unwrap_err();
+        assert_eq!(err.to_string(), "truncated vu64 value");
+        // redundant
+        let bytes = bincode::serialize(&[0xDDu8, 0x00, 0x00][..]).unwrap();
+        assert!(bincode::deserialize::<Vu64>(&bytes).is_err());
+        // trailing bytes
+        let bytes = bincode::serialize(&[0x01u8, 0x02][..]).unwrap();
+        assert!(bincode::deserialize::<Vu64>(&bytes).is_err());
+        // empty
+        let bytes = bincode::serialize(&[0u8; 0][..]).unwrap();
+        assert!(bincode::deserialize::<Vu64>(&bytes).is_err());
+    }
+}
@@ -1,7 +1,22 @@
 /*!
 Supports of encoding signed integers as `Vu64`.
 */
-use crate::{Error, Vu64};
+use crate::{Error, Vu64, MAX_BYTES};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// The zigzag encoded bytes of `i64::MIN`.
+///
+/// `i64::MIN` is zigzag encoded to `u64::MAX`, so all 9 bytes are `0xFF`.
+/// This is the canonical reference for the extreme value.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::signed::{decode, MIN_ENCODED};
+/// assert_eq!(decode(&MIN_ENCODED), Ok(i64::MIN));
+/// ```
+pub const MIN_ENCODED: [u8; MAX_BYTES] = [0xFF; MAX_BYTES];
 
 /// Encode a signed integer as a zigzag-encoded `Vu64`.
 #[inline]
@@ -21,6 +36,100 @@ pub fn encoded_len(value: i64) -> u8 {
     super::encoded_len(zigzag::encode(value))
 }
 
+/// Get the length in bytes of a zigzag encoded `Vu64` of the delta `cur - prev`.
+///
+/// The delta is computed with wrapping subtraction, so it always exists and
+/// `prev.wrapping_add(delta)` gives back `cur`. An adaptive encoder can compare
+/// this with [`encoded_len(cur)`](encoded_len) to pick the smaller representation.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::signed::{delta_len, encoded_len};
+/// assert_eq!(delta_len(1_000_000, 1_000_001), 1);
+/// assert_eq!(encoded_len(1_000_001), 3);
+/// ```
+#[inline]
+pub fn delta_len(prev: i64, cur: i64) -> u8 {
+    encoded_len(cur.wrapping_sub(prev))
+}
+
+/// Get the total length in bytes of the zigzag encoded `Vu64` of all `values`.
+///
+/// This is the exact size of the output of [`pack()`], so a batch encoder
+/// can presize its buffer. Note that the zigzag encoding makes a small negative
+/// value short, but a large magnitude of either sign long.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::signed::encoded_len_slice;
+/// assert_eq!(encoded_len_slice(&[0, -1, 64, i64::MIN]), 1 + 1 + 2 + 9);
+/// ```
+pub fn encoded_len_slice(values: &[i64]) -> usize {
+    values.iter().map(|&v| encoded_len(v) as usize).sum()
+}
+
+/// Decode every zigzag encoded value of `bytes`, and return the sum of them.
+///
+/// The sum is wrapping: if it overflows `i64`, it wraps around,
+/// the same as [`i64::wrapping_add()`].
+///
+/// # Examples
+///
+/// ```
+/// use vu64::signed::{encode, sum_values};
+/// let mut buf = Vec::new();
+/// buf.extend_from_slice(encode(-100).as_ref());
+/// buf.extend_from_slice(encode(30).as_ref());
+/// assert_eq!(sum_values(&buf), Ok(-70));
+/// ```
+pub fn sum_values(mut bytes: &[u8]) -> Result<i64, Error> {
+    let mut sum = 0i64;
+    while !bytes.is_empty() {
+        let (val, len) = super::decode_with_len(bytes)?;
+        sum = sum.wrapping_add(zigzag::decode(val));
+        bytes = &bytes[len..];
+    }
+    Ok(sum)
+}
+
+/// Encode all signed `values` into a new buffer of concatenated zigzag encoded `vu64` values.
+///
+/// This is the signed counterpart of [`vu64::pack()`](crate::pack), and the inverse
+/// of [`unpack()`]: `unpack(&pack(values)) == Ok(values.to_vec())` holds for any `values`.
+///
+/// # Examples
+///
+/// ```
+/// use vu64::signed::{pack, unpack};
+/// let buf = pack(&[-1, 1, i64::MIN]);
+/// assert_eq!(unpack(&buf), Ok(vec![-1, 1, i64::MIN]));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn pack(values: &[i64]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(values.len());
+    for &val in values.iter() {
+        buf.extend_from_slice(encode(val).as_ref());
+    }
+    buf
+}
+
+/// Decode all values of a buffer of concatenated zigzag encoded `vu64` values.
+///
+/// This is the signed counterpart of [`vu64::unpack()`](crate::unpack),
+/// and the inverse of [`pack()`].
+#[cfg(feature = "alloc")]
+pub fn unpack(mut bytes: &[u8]) -> Result<Vec<i64>, Error> {
+    let mut values = Vec::new();
+    while !bytes.is_empty() {
+        let (val, len) = super::decode_with_len(bytes)?;
+        values.push(zigzag::decode(val));
+        bytes = &bytes[len..];
+    }
+    Ok(values)
+}
+
 /// The zigzag encoding for signed integers.
 ///
 /// This module contains the raw zigzag encoding algorithm.
@@ -246,3 +355,118 @@ mod test_i64 {
         assert!(decode(slice).is_err());
     }
 }
+
+#[cfg(test)]
+mod test_i64_delta {
+    use super::super::signed::{delta_len, encoded_len};
+    #[test]
+    fn delta_len_close() {
+        let prev = 0x0f0f_f0f0_0f0f;
+        assert_eq!(delta_len(prev, prev), 1);
+        assert_eq!(delta_len(prev, prev + 63), 1);
+        assert_eq!(delta_len(prev, prev - 64), 1);
+        assert_eq!(delta_len(prev, prev + 64), 2);
+        assert!(delta_len(prev, prev + 100) < encoded_len(prev + 100));
+        assert!(delta_len(-prev, -prev - 100) < encoded_len(-prev - 100));
+    }
+    #[test]
+    fn delta_len_far() {
+        assert_eq!(delta_len(0, 100), encoded_len(100));
+        assert_eq!(delta_len(-0x0f0f_f0f0, 0x0f0f_f0f0), 5);
+        assert_eq!(delta_len(-60, 60), 2);
+        assert!(delta_len(-60, 60) > encoded_len(60));
+    }
+    #[test]
+    fn delta_len_wrapping() {
+        assert_eq!(delta_len(i64::MIN, i64::MAX), encoded_len(-1));
+        assert_eq!(delta_len(i64::MAX, i64::MIN), encoded_len(1));
+        assert_eq!(delta_len(-1, i64::MAX), encoded_len(i64::MIN));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_i64_sum_values {
+    use super::super::signed::{pack, sum_values};
+    use super::super::Error;
+    #[test]
+    fn sum_values_1() {
+        let values = [0, -1, 127, -0x0f0f_f0f0, 0x0f0f_f0f0_0f0f];
+        let buf = pack(&values);
+        assert_eq!(sum_values(&buf), Ok(values.iter().sum()));
+        assert_eq!(sum_values(&[]), Ok(0));
+    }
+    #[test]
+    fn sum_values_wrapping() {
+        let buf = pack(&[i64::MAX, 1]);
+        assert_eq!(sum_values(&buf), Ok(i64::MIN));
+    }
+    #[test]
+    fn sum_values_err() {
+        assert_eq!(sum_values(&[0xDD, 0x00, 0x00]), Err(Error::RedundantEncode));
+    }
+}
+
+#[cfg(test)]
+mod test_i64_min_encoded {
+    use super::super::signed::{decode, encode, zigzag, MIN_ENCODED};
+    #[test]
+    fn min_encoded_1() {
+        assert_eq!(encode(i64::MIN).as_ref(), &[0xFF; 9]);
+        assert_eq!(encode(i64::MIN).as_ref(), &MIN_ENCODED);
+        assert_eq!(zigzag::encode(i64::MIN), u64::MAX);
+        assert_eq!(decode(&MIN_ENCODED), Ok(i64::MIN));
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test_i64_pack {
+    use super::super::signed::{pack, unpack};
+    use super::super::Error;
+    #[test]
+    fn pack_unpack_1() {
+        let values = [
+            0,
+            -1,
+            1,
+            -64,
+            64,
+            0x0f0f_f0f0,
+            -0x0f0f_f0f0_0f0f,
+            i64::MIN,
+            i64::MAX,
+        ];
+        let buf = pack(&values);
+        assert_eq!(unpack(&buf), Ok(values.to_vec()));
+        assert_eq!(pack(&[-1, 1]), vec![0x01, 0x02]);
+        assert_eq!(unpack(&[]), Ok(vec![]));
+    }
+    #[test]
+    fn unpack_err() {
+        assert_eq!(unpack(&[0x01, 0x8F]), Err(Error::Truncated));
+    }
+}
+
+#[cfg(test)]
+mod test_i64_encoded_len_slice {
+    use super::super::signed::{encoded_len, encoded_len_slice};
+    #[test]
+    fn encoded_len_slice_1() {
+        let values = [
+            0,
+            -1,
+            1,
+            -64,
+            64,
+            -0x2000,
+            0x2000,
+            0x0f0f_f0f0,
+            -0x0f0f_f0f0_0f0f,
+            i64::MIN,
+            i64::MAX,
+        ];
+        let manual: usize = values.iter().map(|&v| encoded_len(v) as usize).sum();
+        assert_eq!(encoded_len_slice(&values), manual);
+        assert_eq!(encoded_len_slice(&[-64, 64]), 1 + 2);
+        assert_eq!(encoded_len_slice(&[]), 0);
+    }
+}
@@ -0,0 +1,217 @@
+/*!
+Supports of the variable length integer encoding of u128.
+
+The format is the same as `vu64`: the number of the leading ones of
+the first byte gives the number of the follow bytes. If the first byte is
+all ones, the leading ones continue into the next byte, so the length is
+up to 19 bytes.
+
+A value less than `2^56` is encoded to the same bytes as `vu64`.
+
+| Prefix                           | Precision | Total Bytes |
+|----------------------------------|-----------|-------------|
+| `0xxxxxxx`                       | 7 bits    | 1 byte      |
+| `10xxxxxx`                       | 14 bits   | 2 bytes     |
+| ...                              |           |             |
+| `11111110`                       | 56 bits   | 8 bytes     |
+| `11111111 0xxxxxxx`              | 63 bits   | 9 bytes     |
+| ...                              |           |             |
+| `11111111 11111110`              | 112 bits  | 16 bytes    |
+| `11111111 11111111 0xxxxxxx`     | 119 bits  | 17 bytes    |
+| `11111111 11111111 10xxxxxx`     | 126 bits  | 18 bytes    |
+| `11111111 11111111 110xxxxx`     | 128 bits  | 19 bytes    |
+
+# Examples
+
+```
+use vu64::vu128::{decode, encode};
+assert_eq!(encode(0x0f0f).as_ref(), vu64::encode(0x0f0f).as_ref());
+assert_eq!(decode(encode(u128::MAX).as_ref()), Ok(u128::MAX));
+assert_eq!(encode(u128::MAX).as_ref().len(), 19);
+```
+*/
+use crate::Error;
+use core::fmt::{self, Debug};
+
+/// Maximum length of a `vu128` in bytes
+pub const MAX_BYTES: usize = 19;
+
+/// `vu128`: serialized variable-length 128-bits integers.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct Vu128 {
+    /// Encoded length in bytes
+    length: u8,
+    /// Serialized variable-length integer
+    bytes: [u8; MAX_BYTES],
+}
+
+impl AsRef<[u8]> for Vu128 {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes[..self.length as usize]
+    }
+}
+
+impl Debug for Vu128 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "V128({})", decode(self.as_ref()).unwrap())
+    }
+}
+
+impl From<u128> for Vu128 {
+    #[inline]
+    fn from(value: u128) -> Vu128 {
+        encode(value)
+    }
+}
+
+/// Get the length of an encoded `u128` for the given value in bytes.
+#[inline]
+pub fn encoded_len(value: u128) -> u8 {
+    let bits = 128 - value.leading_zeros();
+    if bits == 0 {
+        1
+    } else {
+        ((bits + 6) / 7) as u8
+    }
+}
+
+/// Encode an unsigned 128-bit integer as `vu128`.
+pub fn encode(value: u128) -> Vu128 {
+    let mut bytes = [0u8; MAX_BYTES];
+    let length = encoded_len(value) as usize;
+    // the bytes holding the prefix, the last one also holds the low bits of the value
+    let prefix_len = (length + 7) / 8;
+    for b in bytes[..prefix_len - 1].iter_mut() {
+        *b = 0xFF;
+    }
+    let ones = (length - 1) - 8 * (prefix_len - 1);
+    let low_bits = 7 - ones;
+    let low = value as u8 & ((1u8 << low_bits) - 1);
+    bytes[prefix_len - 1] = !(0xFFu8 >> ones) | low;
+    //
+    let rest = value >> low_bits;
+    for (i, b) in bytes[prefix_len..length].iter_mut().enumerate() {
+        *b = (rest >> (8 * i)) as u8;
+    }
+    Vu128 {
+        length: length as u8,
+        bytes,
+    }
+}
+
+/// Decode `vu128`-encoded bytes to unsigned 128-bit integer.
+///
+/// Accepts a slice starting with the `vu128`. The bytes after the `vu128` are ignored.
+#[inline]
+pub fn decode(bytes: &[u8]) -> Result<u128, Error> {
+    decode_with_len(bytes).map(|(val, _)| val)
+}
+
+/// Decode `vu128`-encoded bytes to unsigned 128-bit integer, and return it with the consumed length.
+///
+/// If the value of 19 bytes overflows `u128`, `Error::LeadingOnes` is returned.
+pub fn decode_with_len(bytes: &[u8]) -> Result<(u128, usize), Error> {
+    let mut ones = 0;
+    let mut prefix_len = 0;
+    loop {
+        let b = *bytes.get(prefix_len).ok_or(Error::Truncated)?;
+        let n = b.leading_ones() as usize;
+        ones += n;
+        prefix_len += 1;
+        if ones >= MAX_BYTES {
+            return Err(Error::LeadingOnes);
+        }
+        if n < 8 {
+            break;
+        }
+    }
+    let length = ones + 1;
+    if bytes.len() < length {
+        return Err(Error::Truncated);
+    }
+    // the last byte of 19 bytes has only 3 bits of the value
+    if length == MAX_BYTES && bytes[length - 1] >> 3 != 0 {
+        return Err(Error::LeadingOnes);
+    }
+    let low_bits = 7 - (ones - 8 * (prefix_len - 1));
+    let mut result = (bytes[prefix_len - 1] & ((1u8 << low_bits) - 1)) as u128;
+    for (i, &b) in bytes[prefix_len..length].iter().enumerate() {
+        result |= (b as u128) << (low_bits + 8 * i);
+    }
+    // check of the redundant encoding
+    if length == 1 || result >= (1 << (7 * (length - 1))) {
+        Ok((result, length))
+    } else {
+        Err(Error::RedundantEncode)
+    }
+}
+
+#[cfg(test)]
+mod test_vu128 {
+    use super::super::Error;
+    use super::{decode, decode_with_len, encode, encoded_len, MAX_BYTES};
+    #[test]
+    fn vu128_same_as_vu64() {
+        for n in 0..56 {
+            let p = 1u64 << n;
+            for &val in [p - 1, p, p + 1].iter() {
+                let enc = encode(val as u128);
+                assert_eq!(enc.as_ref(), crate::encode(val).as_ref(), "val: {val:#x}");
+            }
+        }
+        assert_eq!(encode(0).as_ref(), &[0x00]);
+        assert_eq!(encode(127).as_ref(), &[0x7F]);
+        assert_eq!(encode(0x0f0f).as_ref(), &[0x8F, 0x3c]);
+    }
+    #[test]
+    fn vu128_round_trip() {
+        for n in 0..128 {
+            let p = 1u128 << n;
+            for &val in [p - 1, p, p + 1].iter() {
+                let enc = encode(val);
+                assert_eq!(enc.as_ref().len(), encoded_len(val) as usize);
+                assert_eq!(decode_with_len(enc.as_ref()), Ok((val, enc.as_ref().len())));
+            }
+        }
+        let enc = encode(u128::MAX);
+        assert_eq!(enc.as_ref().len(), MAX_BYTES);
+        assert_eq!(&enc.as_ref()[..3], &[0xFF, 0xFF, 0xDF]);
+        assert_eq!(decode(enc.as_ref()), Ok(u128::MAX));
+    }
+    #[test]
+    fn vu128_encoded_len() {
+        assert_eq!(encoded_len(0), 1);
+        assert_eq!(encoded_len((1 << 56) - 1), 8);
+        assert_eq!(encoded_len(1 << 56), 9);
+        assert_eq!(encoded_len((1 << 126) - 1), 18);
+        assert_eq!(encoded_len(1 << 126), 19);
+        assert_eq!(encoded_len(u128::MAX), 19);
+    }
+    #[test]
+    fn vu128_decode_err() {
+        assert_eq!(decode(&[]), Err(Error::Truncated));
+        assert_eq!(decode(&[0xFF]), Err(Error::Truncated));
+        assert_eq!(decode(&[0x8F]), Err(Error::Truncated));
+        assert_eq!(decode(&[0xFF, 0xFF, 0xC0]), Err(Error::Truncated));
+        // 20 bytes or more
+        assert_eq!(decode(&[0xFF, 0xFF, 0xE0]), Err(Error::LeadingOnes));
+        assert_eq!(decode(&[0xFF; MAX_BYTES + 1]), Err(Error::LeadingOnes));
+        // 19 bytes overflowing u128
+        let mut buf = [0u8; MAX_BYTES];
+        buf[..3].copy_from_slice(&[0xFF, 0xFF, 0xC0]);
+        buf[MAX_BYTES - 1] = 0xFF;
+        assert_eq!(decode(&buf), Err(Error::LeadingOnes));
+        buf[MAX_BYTES - 1] = 0x08;
+        assert_eq!(decode(&buf), Err(Error::LeadingOnes));
+        buf[MAX_BYTES - 1] = 0x07;
+        assert_eq!(decode(&buf), Ok(0x07 << 125));
+        // 1 in 2 bytes
+        assert_eq!(decode(&[0x81, 0x00]), Err(Error::RedundantEncode));
+        // 1 in 9 bytes
+        let mut buf = [0u8; 9];
+        buf[0] = 0xFF;
+        buf[1] = 0x01;
+        assert_eq!(decode(&buf), Err(Error::RedundantEncode));
+    }
+}
@@ -0,0 +1,92 @@
+/*!
+The derive macro of `vu64` encoding for fieldless enums.
+
+Use it through the `derive` feature of the `vu64` crate.
+
+```
+use vu64::Vu64Codec;
+
+#[derive(Debug, PartialEq, Vu64Codec)]
+enum Op {
+    A,
+    B = 100,
+    C,
+}
+
+let v = Op::C.to_vu64();
+assert_eq!(v.as_ref(), &[0x65]);
+assert_eq!(Op::from_vu64(v.as_ref()), Ok(Op::C));
+assert_eq!(Op::from_vu64(&[0x02]), Err(vu64::Error::UnknownDiscriminant));
+```
+*/
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Derive `to_vu64()` and `from_vu64()` for a fieldless enum.
+///
+/// The discriminant is cast with `as u64`, then encoded as `vu64`.
+/// `from_vu64()` returns `Error::UnknownDiscriminant` for a value
+/// that is not a discriminant of the enum.
+#[proc_macro_derive(Vu64Codec)]
+pub fn derive_vu64_codec(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Vu64Codec can only be derived for enums",
+            ))
+        }
+    };
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Vu64Codec cannot be derived for empty enums",
+        ));
+    }
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Vu64Codec can only be derived for fieldless enums",
+            ));
+        }
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let idents: Vec<_> = data.variants.iter().map(|v| &v.ident).collect();
+    let consts: Vec<_> = idents
+        .iter()
+        .map(|id| format_ident!("__VU64_DISCR_{}", id))
+        .collect();
+    Ok(quote! {
+        #[allow(non_upper_case_globals)]
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(const #consts: u64 = #name::#idents as u64;)*
+
+            /// Encode the discriminant to `vu64`.
+            pub fn to_vu64(&self) -> ::vu64::Vu64 {
+                let discr = match self {
+                    #(#name::#idents => Self::#consts,)*
+                };
+                ::vu64::encode(discr)
+            }
+
+            /// Decode a `vu64`-encoded discriminant.
+            pub fn from_vu64(bytes: &[u8]) -> ::core::result::Result<Self, ::vu64::Error> {
+                match ::vu64::decode(bytes)? {
+                    #(Self::#consts => Ok(#name::#idents),)*
+                    _ => Err(::vu64::Error::UnknownDiscriminant),
+                }
+            }
+        }
+    })
+}
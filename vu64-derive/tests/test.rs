@@ -0,0 +1,43 @@
+use vu64::{Error, Vu64Codec};
+
+#[derive(Debug, Clone, Copy, PartialEq, Vu64Codec)]
+enum Op {
+    A,
+    B = 100,
+    C,
+    D = 0x4000,
+}
+
+#[derive(Debug, PartialEq, Vu64Codec)]
+enum Single {
+    Only = 7,
+}
+
+#[test]
+fn derive_round_trip() {
+    for &op in [Op::A, Op::B, Op::C, Op::D].iter() {
+        let v = op.to_vu64();
+        assert_eq!(vu64::decode(v.as_ref()), Ok(op as u64));
+        assert_eq!(Op::from_vu64(v.as_ref()), Ok(op));
+    }
+    assert_eq!(Op::A.to_vu64().as_ref(), &[0x00]);
+    assert_eq!(Op::C.to_vu64().as_ref(), &[0x65]);
+    assert_eq!(Op::D.to_vu64().as_ref(), &[0xC0, 0x00, 0x02]);
+}
+
+#[test]
+fn derive_unknown_discriminant() {
+    assert_eq!(Op::from_vu64(&[0x01]), Err(Error::UnknownDiscriminant));
+    assert_eq!(
+        Op::from_vu64(vu64::encode(u64::MAX).as_ref()),
+        Err(Error::UnknownDiscriminant)
+    );
+    assert_eq!(Single::from_vu64(&[0x07]), Ok(Single::Only));
+    assert_eq!(Single::from_vu64(&[0x00]), Err(Error::UnknownDiscriminant));
+}
+
+#[test]
+fn derive_decode_error() {
+    assert_eq!(Op::from_vu64(&[]), Err(Error::Truncated));
+    assert_eq!(Op::from_vu64(&[0x80, 0x00]), Err(Error::RedundantEncode));
+}
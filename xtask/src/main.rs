@@ -34,6 +34,6 @@ fn print_version_and_exit(_program: &str) {
 }
 
 fn print_help_and_exit(program: &str) {
-    println!("[usage] {} {{ {} }}", program, concat!("tester",));
+    println!("[usage] {} {{ tester }}", program);
     std::process::exit(0);
 }